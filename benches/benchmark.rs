@@ -57,7 +57,7 @@ pub fn invert(c: &mut Criterion) {
     c.bench_function("invert", |b| {
         b.iter(|| {
             for (o, s) in input.iter() {
-                let _ = o.invert(black_box(&s));
+                let _ = o.invert(black_box(s));
             }
         })
     });
@@ -75,7 +75,7 @@ pub fn apply(c: &mut Criterion) {
     c.bench_function("apply", |b| {
         b.iter(|| {
             for (o, s) in input.iter() {
-                let _ = o.apply(black_box(&s));
+                let _ = o.apply(black_box(s));
             }
         })
     });
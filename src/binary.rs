@@ -0,0 +1,230 @@
+//! A compact binary encoding of operations on text.
+//!
+//! Every operation is encoded as a single unsigned LEB128 varint holding its
+//! count in the upper bits and its kind in the lowest two bits:
+//!
+//! - `n << 2 | 0`: Retain(n)
+//! - `n << 2 | 1`: Delete(n)
+//! - `n << 2 | 2`: Insert(s), followed by the `n` bytes of `s` in UTF-8
+//! - `n << 2 | 3`: the base length `n` of the operation, which is only
+//!   allowed in front of all other operations
+//!
+//! Typical edits like typing a character into a document take a few bytes,
+//! while the JSON of the `serde` feature needs about twice as much.
+//!
+//! ```rust
+//! use operational_transform::OperationSeq;
+//!
+//! let mut o = OperationSeq::default();
+//! o.retain(100);
+//! o.insert("a");
+//! o.retain(50);
+//! let bytes = o.encode(false);
+//! assert_eq!(bytes, vec![0x90, 0x03, 0x06, b'a', 0xc8, 0x01]);
+//! assert_eq!(OperationSeq::decode(&bytes).unwrap(), o);
+//! ```
+
+use crate::{sequence::Sequence, DecodeErrorKind, GenericOperation, GenericOperationSeq, OTError};
+use std::convert::TryFrom;
+
+const RETAIN: u128 = 0;
+const DELETE: u128 = 1;
+const INSERT: u128 = 2;
+const BASE_LEN: u128 = 3;
+
+impl<S: Sequence<Slice = str, Owned = String>> GenericOperationSeq<S> {
+    /// Encodes the operation into its binary form. If `include_base_len` is
+    /// set, the base length is encoded as well and checked when decoding.
+    pub fn encode(&self, include_base_len: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 * self.ops.len());
+        if include_base_len {
+            write_varint(&mut bytes, (self.base_len as u128) << 2 | BASE_LEN);
+        }
+        for op in &self.ops {
+            match op {
+                GenericOperation::Retain(n) => write_varint(&mut bytes, (*n as u128) << 2 | RETAIN),
+                GenericOperation::Delete(n) => write_varint(&mut bytes, (*n as u128) << 2 | DELETE),
+                GenericOperation::Insert(s) => {
+                    write_varint(&mut bytes, (s.as_slice().len() as u128) << 2 | INSERT);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Decodes an operation from its binary form.
+    ///
+    /// # Error
+    ///
+    /// Returns `OTError::Decode` with the offset of the problem if `bytes` are
+    /// malformed, or an `OTError` if the operation does not have the encoded
+    /// base length.
+    pub fn decode(bytes: &[u8]) -> Result<Self, OTError> {
+        let mut o = GenericOperationSeq::default();
+        let mut base_len = None;
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let offset = bytes.len() - rest.len();
+            let value =
+                read_varint(&mut rest).ok_or(invalid(DecodeErrorKind::InvalidVarint, offset))?;
+            // The lengths of the operation must not overflow.
+            let n = usize::try_from(value >> 2)
+                .map_err(|_| invalid(DecodeErrorKind::CountOverflow, offset))?;
+            let base_len_ok = o.base_len.checked_add(n).is_some();
+            let target_len_ok = o.target_len.checked_add(n).is_some();
+            match value & 0b11 {
+                RETAIN if base_len_ok && target_len_ok => o.retain(n as u64),
+                DELETE if base_len_ok => o.delete(n as u64),
+                INSERT if target_len_ok => {
+                    if n > rest.len() {
+                        return Err(invalid(DecodeErrorKind::InsertExceedsInput, offset));
+                    }
+                    let (s, tail) = rest.split_at(n);
+                    let s = std::str::from_utf8(s)
+                        .map_err(|_| invalid(DecodeErrorKind::InvalidUtf8, offset))?;
+                    o.insert(s);
+                    rest = tail;
+                }
+                BASE_LEN if offset == 0 => base_len = Some(n),
+                RETAIN | DELETE | INSERT => {
+                    return Err(invalid(DecodeErrorKind::CountOverflow, offset))
+                }
+                _ => return Err(invalid(DecodeErrorKind::UnexpectedBaseLength, offset)),
+            }
+        }
+        match base_len {
+            Some(base_len) if base_len != o.base_len => Err(OTError::BaseLengthMismatch {
+                expected: base_len,
+                actual: o.base_len,
+            }),
+            _ => Ok(o),
+        }
+    }
+}
+
+// Creates the error for malformed input at `offset`.
+fn invalid(kind: DecodeErrorKind, offset: usize) -> OTError {
+    OTError::Decode { kind, offset }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// Reads a varint from the front of `bytes` and advances it. Returns `None` if
+// the varint is truncated or overlong.
+fn read_varint(bytes: &mut &[u8]) -> Option<u128> {
+    let mut value = 0u128;
+    for (i, byte) in bytes.iter().enumerate() {
+        // The count of an operation plus its kind fits into 66 bits.
+        if i == 10 {
+            break;
+        }
+        value |= ((byte & 0x7f) as u128) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utilities::Rng, OperationSeq, Utf16OperationSeq};
+    use rand::{prelude::*, Rng as _};
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            assert_eq!(OperationSeq::decode(&o.encode(false)).unwrap(), o);
+            assert_eq!(OperationSeq::decode(&o.encode(true)).unwrap(), o);
+            let o: Utf16OperationSeq = o.convert(&s).unwrap();
+            assert_eq!(Utf16OperationSeq::decode(&o.encode(true)).unwrap(), o);
+        }
+
+        let mut o = OperationSeq::default();
+        o.retain(u32::MAX as u64);
+        o.delete(u32::MAX as u64);
+        assert_eq!(OperationSeq::decode(&o.encode(false)).unwrap(), o);
+        assert_eq!(OperationSeq::decode(&[]).unwrap(), OperationSeq::default());
+    }
+
+    #[test]
+    fn base_len() {
+        let mut o = OperationSeq::default();
+        o.retain(2);
+        o.insert("ab");
+        let bytes = o.encode(true);
+        assert_eq!(bytes, vec![0x0b, 0x08, 0x0a, b'a', b'b']);
+        assert_eq!(
+            OperationSeq::decode(&[0x0f, 0x08]),
+            Err(OTError::BaseLengthMismatch {
+                expected: 3,
+                actual: 2
+            })
+        );
+        let err = OperationSeq::decode(&[0x08, 0x0b]).unwrap_err();
+        assert_eq!(
+            err,
+            OTError::Decode {
+                kind: DecodeErrorKind::UnexpectedBaseLength,
+                offset: 1
+            }
+        );
+        assert_eq!(err.to_string(), "unexpected base length at byte 1");
+    }
+
+    #[test]
+    fn malformed() {
+        let invalid_inputs: &[(&[u8], DecodeErrorKind, usize)] = &[
+            // Truncated varint.
+            (&[0x80], DecodeErrorKind::InvalidVarint, 0),
+            // Overlong varint.
+            (&[0x80; 11], DecodeErrorKind::InvalidVarint, 0),
+            // Count exceeding u64.
+            (
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+                DecodeErrorKind::CountOverflow,
+                0,
+            ),
+            // Retains overflowing the base length.
+            (
+                &[
+                    0xfc, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x07, 0x04,
+                ],
+                DecodeErrorKind::CountOverflow,
+                10,
+            ),
+            // Insert longer than the input.
+            (&[0x0a, b'a'], DecodeErrorKind::InsertExceedsInput, 0),
+            // Insert of invalid UTF-8 after a retain.
+            (&[0x04, 0x06, 0xff], DecodeErrorKind::InvalidUtf8, 1),
+        ];
+        for &(bytes, kind, offset) in invalid_inputs {
+            assert_eq!(
+                OperationSeq::decode(bytes),
+                Err(OTError::Decode { kind, offset }),
+                "{:?}",
+                bytes
+            );
+        }
+
+        // Random input must never panic.
+        let mut rng = StdRng::from_entropy();
+        for _ in 0..10000 {
+            let len = rng.gen_range(0, 20);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let _ = OperationSeq::decode(&bytes);
+        }
+    }
+}
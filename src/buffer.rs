@@ -0,0 +1,207 @@
+//! Operations applied in place to text buffers.
+//!
+//! Editors usually own their text in a data structure of their own. By
+//! implementing [`TextBuffer`] for it, an `OperationSeq` edits the buffer
+//! directly instead of producing a new string.
+//!
+//! ```rust
+//! use operational_transform::{buffer::TextBuffer, OperationSeq};
+//! use std::ops::Range;
+//!
+//! // A buffer which only records the edits.
+//! struct Recorder {
+//!     len: usize,
+//!     edits: Vec<String>,
+//! }
+//!
+//! impl TextBuffer for Recorder {
+//!     fn len_chars(&self) -> usize {
+//!         self.len
+//!     }
+//!
+//!     fn insert(&mut self, at: usize, s: &str) {
+//!         self.len += s.chars().count();
+//!         self.edits.push(format!("insert {:?} at {}", s, at));
+//!     }
+//!
+//!     fn delete(&mut self, range: Range<usize>) {
+//!         self.len -= range.len();
+//!         self.edits.push(format!("delete {:?}", range));
+//!     }
+//! }
+//!
+//! let mut o = OperationSeq::default();
+//! o.insert("abc");
+//! o.retain(2);
+//! o.delete(1);
+//!
+//! let mut recorder = Recorder { len: 3, edits: Vec::new() };
+//! o.apply_in_place(&mut recorder).unwrap();
+//! assert_eq!(recorder.edits, vec!["insert \"abc\" at 0", "delete 5..6"]);
+//!
+//! let mut s = "lor".to_owned();
+//! o.apply_in_place(&mut s).unwrap();
+//! assert_eq!(s, "abclo");
+//! ```
+
+use crate::{OTError, Operation, OperationSeq};
+use bytecount::num_chars;
+use std::ops::Range;
+
+/// A mutable text that operations can be applied to in place. All positions
+/// are counted in characters.
+pub trait TextBuffer {
+    /// Returns the length of the text in characters.
+    fn len_chars(&self) -> usize;
+
+    /// Inserts `s` in front of the character at `at`.
+    fn insert(&mut self, at: usize, s: &str);
+
+    /// Deletes the characters in `range`.
+    fn delete(&mut self, range: Range<usize>);
+
+    /// Applies `ops`, whose retains and deletes add up to the length of the
+    /// buffer, from front to back. The default implementation passes every
+    /// edit to [`insert`](Self::insert) and [`delete`](Self::delete); buffers
+    /// which can keep their position between edits override it.
+    fn apply_ops(&mut self, ops: &[Operation]) {
+        // The position in the buffer in characters.
+        let mut pos = 0;
+        for op in ops {
+            match op {
+                Operation::Retain(retain) => pos += *retain as usize,
+                Operation::Delete(delete) => self.delete(pos..pos + *delete as usize),
+                Operation::Insert(insert) => {
+                    self.insert(pos, insert);
+                    pos += insert.unit_len();
+                }
+            }
+        }
+    }
+}
+
+impl TextBuffer for String {
+    #[inline]
+    fn len_chars(&self) -> usize {
+        num_chars(self.as_bytes())
+    }
+
+    fn insert(&mut self, at: usize, s: &str) {
+        let i = byte_index(self, at);
+        self.insert_str(i, s);
+    }
+
+    fn delete(&mut self, range: Range<usize>) {
+        let start = byte_index(self, range.start);
+        let end = start + byte_index(&self[start..], range.len());
+        self.replace_range(start..end, "");
+    }
+
+    fn apply_ops(&mut self, ops: &[Operation]) {
+        // The position in the buffer in bytes. Every component continues
+        // where the previous one stopped, so the characters in front of it
+        // are never counted again.
+        let mut pos = 0;
+        for op in ops {
+            match op {
+                Operation::Retain(retain) => pos += byte_index(&self[pos..], *retain as usize),
+                Operation::Delete(delete) => {
+                    let end = pos + byte_index(&self[pos..], *delete as usize);
+                    self.replace_range(pos..end, "");
+                }
+                Operation::Insert(insert) => {
+                    self.insert_str(pos, insert);
+                    pos += insert.len();
+                }
+            }
+        }
+    }
+}
+
+// Returns the byte index of the character at `at` in `s`.
+fn byte_index(s: &str, at: usize) -> usize {
+    s.char_indices().nth(at).map_or(s.len(), |(i, _)| i)
+}
+
+impl OperationSeq {
+    /// Applies the operation to `buffer` in place. The edits are passed to
+    /// the buffer from front to back, so the positions of every edit are
+    /// valid for the buffer with all previous edits applied.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `buffer` does not have the base length of the
+    /// operation. The buffer is left unchanged in that case.
+    pub fn apply_in_place<B: TextBuffer + ?Sized>(&self, buffer: &mut B) -> Result<(), OTError> {
+        if buffer.len_chars() != self.base_len {
+            return Err(OTError::BaseLengthMismatch {
+                expected: self.base_len,
+                actual: buffer.len_chars(),
+            });
+        }
+        buffer.apply_ops(&self.ops);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+
+    #[test]
+    fn string() {
+        let mut s = "añb".to_owned();
+        TextBuffer::insert(&mut s, 2, "xy");
+        assert_eq!(s, "añxyb");
+        TextBuffer::delete(&mut s, 1..3);
+        assert_eq!(s, "ayb");
+        TextBuffer::insert(&mut s, 3, "z");
+        assert_eq!(s, "aybz");
+        assert_eq!(s.len_chars(), 4);
+    }
+
+    // Edits a string through the default implementation of `apply_ops`.
+    struct Edits(String);
+
+    impl TextBuffer for Edits {
+        fn len_chars(&self) -> usize {
+            self.0.len_chars()
+        }
+
+        fn insert(&mut self, at: usize, s: &str) {
+            TextBuffer::insert(&mut self.0, at, s)
+        }
+
+        fn delete(&mut self, range: Range<usize>) {
+            TextBuffer::delete(&mut self.0, range)
+        }
+    }
+
+    #[test]
+    fn apply_in_place() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let mut buffer = s.clone();
+            o.apply_in_place(&mut buffer).unwrap();
+            assert_eq!(buffer, o.apply(&s).unwrap());
+            let mut edits = Edits(s.clone());
+            o.apply_in_place(&mut edits).unwrap();
+            assert_eq!(edits.0, buffer);
+        }
+
+        let mut o = OperationSeq::default();
+        o.retain(2);
+        let mut s = "abc".to_owned();
+        assert_eq!(
+            o.apply_in_place(&mut s),
+            Err(OTError::BaseLengthMismatch {
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(s, "abc");
+    }
+}
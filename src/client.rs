@@ -0,0 +1,259 @@
+//! Client side synchronization with a central server.
+//!
+//! A client can only have one operation in flight at a time. While it waits
+//! for the server to acknowledge that operation, all further local edits are
+//! composed into a single buffered operation which is sent as soon as the
+//! acknowledgement arrives. Operations received from the server in the
+//! meantime are transformed against the outstanding and the buffered
+//! operation.
+//!
+//! ```rust
+//! use operational_transform::{client::Client, OperationSeq};
+//!
+//! let mut client = Client::new(0);
+//!
+//! let mut local = OperationSeq::default();
+//! local.insert("abc");
+//! // The first local edit is sent to the server right away.
+//! let (revision, sent) = client.apply_client(local.clone()).unwrap().unwrap();
+//! assert_eq!(revision, 0);
+//! assert_eq!(sent, local);
+//!
+//! let mut remote = OperationSeq::default();
+//! remote.insert("def");
+//! // A concurrent operation from the server is transformed before it is
+//! // applied to the local document.
+//! let remote_prime = client.apply_server(&remote).unwrap();
+//! assert_eq!(remote_prime.apply("abc").unwrap(), "abcdef");
+//!
+//! // Nothing was buffered, so there is nothing left to send.
+//! assert!(client.server_ack().unwrap().is_none());
+//! assert_eq!(client.revision(), 2);
+//! ```
+
+use crate::{OTError, OperationSeq};
+
+/// The synchronization state of a [`Client`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum State {
+    /// All local operations have been acknowledged by the server.
+    Synchronized,
+    /// An operation has been sent to the server and waits for its
+    /// acknowledgement.
+    AwaitingConfirm(OperationSeq),
+    /// An operation has been sent to the server and waits for its
+    /// acknowledgement. Local operations that happened since are buffered.
+    AwaitingWithBuffer(OperationSeq, OperationSeq),
+}
+
+/// Keeps track of the revision and the pending operations of a client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Client {
+    // The number of operations the server has acknowledged or sent so far.
+    revision: usize,
+    state: State,
+}
+
+impl Client {
+    /// Creates a synchronized client at the server's `revision`.
+    pub fn new(revision: usize) -> Self {
+        Self {
+            revision,
+            state: State::Synchronized,
+        }
+    }
+
+    /// Returns the latest server revision known to the client.
+    #[inline]
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// Returns the current synchronization state.
+    #[inline]
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Handles an operation the user applied to the local document. Returns
+    /// the operation together with the revision it is based on if it should be
+    /// sent to the server right away.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operation cannot be composed with the
+    /// buffered operation.
+    pub fn apply_client(
+        &mut self,
+        operation: OperationSeq,
+    ) -> Result<Option<(usize, OperationSeq)>, OTError> {
+        let (state, send) = match std::mem::replace(&mut self.state, State::Synchronized) {
+            State::Synchronized => {
+                let send = Some((self.revision, operation.clone()));
+                (State::AwaitingConfirm(operation), send)
+            }
+            State::AwaitingConfirm(outstanding) => {
+                (State::AwaitingWithBuffer(outstanding, operation), None)
+            }
+            State::AwaitingWithBuffer(outstanding, buffer) => {
+                let buffer = match buffer.compose(&operation) {
+                    Ok(buffer) => buffer,
+                    Err(err) => {
+                        self.state = State::AwaitingWithBuffer(outstanding, buffer);
+                        return Err(err);
+                    }
+                };
+                (State::AwaitingWithBuffer(outstanding, buffer), None)
+            }
+        };
+        self.state = state;
+        Ok(send)
+    }
+
+    /// Handles an operation received from the server. Returns the operation
+    /// which has to be applied to the local document.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operation cannot be transformed against the
+    /// pending operations. The state of the client is left unchanged in that
+    /// case.
+    pub fn apply_server(&mut self, operation: &OperationSeq) -> Result<OperationSeq, OTError> {
+        let (state, operation) = match &self.state {
+            State::Synchronized => (State::Synchronized, operation.clone()),
+            State::AwaitingConfirm(outstanding) => {
+                let (outstanding, operation) = outstanding.transform(operation)?;
+                (State::AwaitingConfirm(outstanding), operation)
+            }
+            State::AwaitingWithBuffer(outstanding, buffer) => {
+                let (outstanding, operation) = outstanding.transform(operation)?;
+                let (buffer, operation) = buffer.transform(&operation)?;
+                (State::AwaitingWithBuffer(outstanding, buffer), operation)
+            }
+        };
+        self.revision += 1;
+        self.state = state;
+        Ok(operation)
+    }
+
+    /// Handles the server's acknowledgement of the outstanding operation.
+    /// Returns the buffered operation together with the revision it is based
+    /// on if there is one which now has to be sent to the server.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if there is no outstanding operation.
+    pub fn server_ack(&mut self) -> Result<Option<(usize, OperationSeq)>, OTError> {
+        let (state, send) = match std::mem::replace(&mut self.state, State::Synchronized) {
+            State::Synchronized => return Err(OTError::NoPendingOperation),
+            State::AwaitingConfirm(_) => (State::Synchronized, None),
+            State::AwaitingWithBuffer(_, buffer) => {
+                let send = Some((self.revision + 1, buffer.clone()));
+                (State::AwaitingConfirm(buffer), send)
+            }
+        };
+        self.revision += 1;
+        self.state = state;
+        Ok(send)
+    }
+
+    /// Returns the outstanding operation together with the revision it is
+    /// based on if it has to be sent again after the connection to the server
+    /// was reestablished.
+    pub fn server_reconnect(&self) -> Option<(usize, OperationSeq)> {
+        match &self.state {
+            State::Synchronized => None,
+            State::AwaitingConfirm(outstanding) | State::AwaitingWithBuffer(outstanding, _) => {
+                Some((self.revision, outstanding.clone()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+
+    #[test]
+    fn states() {
+        let mut client = Client::new(1);
+        let mut a = OperationSeq::default();
+        a.insert("a");
+        let mut b = OperationSeq::default();
+        b.retain(1);
+        b.insert("b");
+        let mut c = OperationSeq::default();
+        c.retain(2);
+        c.insert("c");
+
+        assert_eq!(client.server_reconnect(), None);
+        assert_eq!(
+            client.apply_client(a.clone()).unwrap(),
+            Some((1, a.clone()))
+        );
+        assert_eq!(client.state(), &State::AwaitingConfirm(a.clone()));
+        assert_eq!(client.apply_client(b.clone()).unwrap(), None);
+        assert_eq!(client.apply_client(c.clone()).unwrap(), None);
+        let bc = b.compose(&c).unwrap();
+        assert_eq!(
+            client.state(),
+            &State::AwaitingWithBuffer(a.clone(), bc.clone())
+        );
+        assert_eq!(client.server_reconnect(), Some((1, a)));
+        assert_eq!(client.server_ack().unwrap(), Some((2, bc.clone())));
+        assert_eq!(client.state(), &State::AwaitingConfirm(bc));
+        assert_eq!(client.server_ack().unwrap(), None);
+        assert_eq!(client.state(), &State::Synchronized);
+        assert_eq!(client.revision(), 3);
+        assert_eq!(client.server_ack(), Err(OTError::NoPendingOperation));
+    }
+
+    #[test]
+    fn converge() {
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let s = rng.gen_string(20);
+            let mut client = Client::new(0);
+            let mut client_doc = s.clone();
+            let mut server_doc = s.clone();
+            let mut server_ops: Vec<OperationSeq> = Vec::new();
+            let mut in_flight: Option<(usize, OperationSeq)> = None;
+
+            for _ in 0..10 {
+                let local = rng.gen_operation_seq(&client_doc);
+                client_doc = local.apply(&client_doc).unwrap();
+                if let Some(send) = client.apply_client(local).unwrap() {
+                    in_flight = Some(send);
+                }
+
+                let remote = rng.gen_operation_seq(&server_doc);
+                server_doc = remote.apply(&server_doc).unwrap();
+                server_ops.push(remote.clone());
+                let remote_prime = client.apply_server(&remote).unwrap();
+                client_doc = remote_prime.apply(&client_doc).unwrap();
+
+                if let Some((revision, mut op)) = in_flight.take() {
+                    for concurrent in &server_ops[revision..] {
+                        op = op.transform(concurrent).unwrap().0;
+                    }
+                    server_doc = op.apply(&server_doc).unwrap();
+                    server_ops.push(op);
+                    in_flight = client.server_ack().unwrap();
+                }
+            }
+            while let Some((revision, mut op)) = in_flight.take() {
+                for concurrent in &server_ops[revision..] {
+                    op = op.transform(concurrent).unwrap().0;
+                }
+                server_doc = op.apply(&server_doc).unwrap();
+                server_ops.push(op);
+                in_flight = client.server_ack().unwrap();
+            }
+
+            assert_eq!(client.state(), &State::Synchronized);
+            assert_eq!(client.revision(), server_ops.len());
+            assert_eq!(client_doc, server_doc);
+        }
+    }
+}
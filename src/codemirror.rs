@@ -0,0 +1,276 @@
+//! The JSON form of CodeMirror 6 change sets.
+//!
+//! `ChangeSet.toJSON` produces a list of sections covering the whole
+//! document:
+//!
+//! - `n`: keeps `n` code units
+//! - `[n]`: deletes `n` code units
+//! - `[n, ...lines]`: replaces `n` code units with the lines joined by `\n`
+//!
+//! CodeMirror counts UTF-16 code units, so a change set maps directly to a
+//! [`Utf16OperationSeq`]. An [`OperationSeq`] needs the document the change
+//! set applies to in order to count characters instead. This module is
+//! available with the `serde` feature.
+//!
+//! ```rust
+//! use operational_transform::{codemirror::Section, OperationSeq};
+//!
+//! let doc = "a😀b";
+//! let sections: Vec<Section> = serde_json::from_str(r#"[1,[2,"x","y"],1]"#).unwrap();
+//! let o = OperationSeq::from_codemirror_changeset(&sections, doc).unwrap();
+//! assert_eq!(o.base_len(), 3);
+//! assert_eq!(o.apply(doc).unwrap(), "ax\nyb");
+//! assert_eq!(o.to_codemirror_changeset(doc).unwrap(), sections);
+//! ```
+
+use crate::{
+    sequence::Sequence, serde::OwnedString, GenericOperation, OTError, OperationSeq, Utf16,
+    Utf16OperationSeq,
+};
+use serde::{
+    de::{self, Deserializer, SeqAccess, Visitor},
+    ser::{SerializeSeq, Serializer},
+    Deserialize, Serialize,
+};
+use std::{convert::TryFrom, fmt};
+
+/// A section of a CodeMirror change set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Section {
+    /// Keeps the given number of code units.
+    Retain(u64),
+    /// Replaces the given number of code units with the lines of text. No
+    /// lines delete the code units only.
+    Replace(u64, Vec<String>),
+}
+
+impl Utf16OperationSeq {
+    /// Creates an operation from the `sections` of a CodeMirror change set.
+    ///
+    /// # Error
+    ///
+    /// Returns `OTError::CountOverflow` if a count of a section overflows the
+    /// lengths of the operation.
+    pub fn from_codemirror_changeset(sections: &[Section]) -> Result<Self, OTError> {
+        let mut o = Self::with_capacity(sections.len());
+        for (index, section) in sections.iter().enumerate() {
+            // The counts are untrusted, so the lengths are checked before the
+            // operation grows.
+            let overflows = |n: u64, len: usize| {
+                usize::try_from(n)
+                    .ok()
+                    .and_then(|n| len.checked_add(n))
+                    .is_none()
+            };
+            match section {
+                Section::Retain(n) => {
+                    if overflows(*n, o.base_len) || overflows(*n, o.target_len) {
+                        return Err(OTError::CountOverflow { index });
+                    }
+                    o.retain(*n);
+                }
+                Section::Replace(n, lines) => {
+                    let s = lines.join("\n");
+                    if overflows(*n, o.base_len) || overflows(Utf16::len(&s) as u64, o.target_len) {
+                        return Err(OTError::CountOverflow { index });
+                    }
+                    o.insert(&s);
+                    o.delete(*n);
+                }
+            }
+        }
+        Ok(o)
+    }
+
+    /// Converts the operation into the sections of a CodeMirror change set.
+    /// Inserts are split into lines at `\n`.
+    pub fn to_codemirror_changeset(&self) -> Vec<Section> {
+        let mut sections = Vec::with_capacity(self.ops.len());
+        // The deletion and insertion of the pending replace section.
+        let mut replace: Option<(u64, String)> = None;
+        for op in &self.ops {
+            match op {
+                GenericOperation::Retain(n) => {
+                    if let Some(replace) = replace.take() {
+                        sections.push(replace_section(replace));
+                    }
+                    sections.push(Section::Retain(*n));
+                }
+                GenericOperation::Delete(n) => replace.get_or_insert_with(Default::default).0 += n,
+                GenericOperation::Insert(s) => {
+                    replace
+                        .get_or_insert_with(Default::default)
+                        .1
+                        .push_str(s.as_slice());
+                }
+            }
+        }
+        if let Some(replace) = replace {
+            sections.push(replace_section(replace));
+        }
+        sections
+    }
+}
+
+// Creates the section replacing `n` code units with `s`.
+fn replace_section((n, s): (u64, String)) -> Section {
+    let lines = if s.is_empty() {
+        Vec::new()
+    } else {
+        s.split('\n').map(str::to_owned).collect()
+    };
+    Section::Replace(n, lines)
+}
+
+impl OperationSeq {
+    /// Creates an operation from the `sections` of a CodeMirror change set
+    /// on `doc`.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `doc` does not have the length of the change
+    /// set or if a count overflows.
+    pub fn from_codemirror_changeset(sections: &[Section], doc: &str) -> Result<Self, OTError> {
+        Utf16OperationSeq::from_codemirror_changeset(sections)?.convert(doc)
+    }
+
+    /// Converts the operation on `doc` into the sections of a CodeMirror
+    /// change set.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `doc` does not have the base length of the
+    /// operation.
+    pub fn to_codemirror_changeset(&self, doc: &str) -> Result<Vec<Section>, OTError> {
+        Ok(self.convert::<crate::Utf16>(doc)?.to_codemirror_changeset())
+    }
+}
+
+impl Serialize for Section {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Section::Retain(n) => serializer.serialize_u64(*n),
+            Section::Replace(n, lines) => {
+                let mut seq = serializer.serialize_seq(Some(1 + lines.len()))?;
+                seq.serialize_element(n)?;
+                for line in lines {
+                    seq.serialize_element(line.as_str())?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Section {
+    fn deserialize<D>(deserializer: D) -> Result<Section, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SectionVisitor;
+
+        impl<'de> Visitor<'de> for SectionVisitor {
+            type Value = Section;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a length or a length followed by lines")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Section::Retain(value))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let n = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let mut lines = Vec::new();
+                while let Some(OwnedString(line)) = seq.next_element()? {
+                    lines.push(line);
+                }
+                Ok(Section::Replace(n, lines))
+            }
+        }
+
+        deserializer.deserialize_any(SectionVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+
+    // Change sets as CodeMirror serializes them with the document before and
+    // after.
+    const FIXTURES: &[(&str, &str, &str)] = &[
+        ("[]", "", ""),
+        ("[3]", "abc", "abc"),
+        (r#"[[0,"hi"],3]"#, "abc", "hiabc"),
+        ("[[3]]", "abc", ""),
+        (r#"[1,[1,"x","y"],1]"#, "abc", "ax\nyc"),
+        (r#"[[0,"","",""]]"#, "", "\n\n"),
+        (r#"[1,[2,"c"],1]"#, "a😀b", "acb"),
+        (r#"[[2,"😀"],1,[0,"z"]]"#, "😀b", "😀bz"),
+    ];
+
+    #[test]
+    fn fixtures() {
+        for (json, before, after) in FIXTURES {
+            let sections: Vec<Section> = serde_json::from_str(json).unwrap();
+            let o = OperationSeq::from_codemirror_changeset(&sections, before).unwrap();
+            assert_eq!(o.apply(before).unwrap(), *after);
+            let json_o = serde_json::to_string(&o.to_codemirror_changeset(before).unwrap());
+            assert_eq!(json_o.unwrap(), *json);
+        }
+
+        assert_eq!(
+            OperationSeq::from_codemirror_changeset(&[Section::Retain(2)], "😀b"),
+            Err(OTError::BaseLengthMismatch {
+                expected: 2,
+                actual: 3
+            })
+        );
+        let sections = [Section::Retain(1), Section::Replace(u64::MAX, Vec::new())];
+        assert_eq!(
+            Utf16OperationSeq::from_codemirror_changeset(&sections),
+            Err(OTError::CountOverflow { index: 1 })
+        );
+        assert_eq!(
+            OperationSeq::from_codemirror_changeset(&sections, "a"),
+            Err(OTError::CountOverflow { index: 1 })
+        );
+        for json in &["[-1]", r#"["a"]"#, "[[]]", r#"[["a"]]"#, "[[1,2]]"] {
+            assert!(serde_json::from_str::<Vec<Section>>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let sections = o.to_codemirror_changeset(&s).unwrap();
+            assert_eq!(
+                OperationSeq::from_codemirror_changeset(&sections, &s).unwrap(),
+                o
+            );
+            let o: Utf16OperationSeq = o.convert(&s).unwrap();
+            assert_eq!(o.to_codemirror_changeset(), sections);
+            assert_eq!(
+                Utf16OperationSeq::from_codemirror_changeset(&sections),
+                Ok(o)
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Operations computed from the difference of two texts.
+//!
+//! If only the old and the new version of a document are known, e.g. because
+//! the file was changed by an external tool, the operation that turns one
+//! into the other is computed with Myers' diff algorithm. The result is
+//! minimal in the number of deleted and inserted units of the chosen
+//! [`Granularity`].
+//!
+//! ```rust
+//! use operational_transform::{diff::Granularity, OperationSeq};
+//!
+//! let o = OperationSeq::from_diff("lorem ipsum", "lorem dolor ipsum");
+//! assert_eq!(o.apply("lorem ipsum").unwrap(), "lorem dolor ipsum");
+//!
+//! let o = OperationSeq::from_diff_with("a\nb\nc\n", "a\nc\n", Granularity::Line);
+//! assert_eq!(o.apply("a\nb\nc\n").unwrap(), "a\nc\n");
+//! ```
+
+use crate::OperationSeq;
+use bytecount::num_chars;
+use std::ops::{Index, IndexMut};
+
+/// The units two texts are compared in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    /// Every character is compared on its own.
+    Char,
+    /// Runs of alphanumeric characters and runs of whitespace are compared as
+    /// a whole. All other characters are compared on their own.
+    Word,
+    /// Lines including their line break are compared as a whole.
+    Line,
+}
+
+impl OperationSeq {
+    /// Computes a minimal operation that turns `old` into `new` by comparing
+    /// them character by character.
+    pub fn from_diff(old: &str, new: &str) -> Self {
+        Self::from_diff_with(old, new, Granularity::Char)
+    }
+
+    /// Computes an operation that turns `old` into `new`. The operation is
+    /// minimal with respect to the units of `granularity`. Coarser units are
+    /// faster to compare and produce diffs that are easier to read.
+    pub fn from_diff_with(old: &str, new: &str, granularity: Granularity) -> Self {
+        let old = tokenize(old, granularity);
+        let new = tokenize(new, granularity);
+        let mut vf = V::new(max_d(old.len(), new.len()));
+        let mut vb = V::new(max_d(old.len(), new.len()));
+        let mut o = OperationSeq::default();
+        conquer(&old, &new, &mut vf, &mut vb, &mut o);
+        o
+    }
+}
+
+// Splits `s` into the units that are compared.
+fn tokenize(s: &str, granularity: Granularity) -> Vec<&str> {
+    match granularity {
+        Granularity::Char => s
+            .char_indices()
+            .map(|(i, c)| &s[i..i + c.len_utf8()])
+            .collect(),
+        Granularity::Word => {
+            let class = |c: char| {
+                if c.is_alphanumeric() || c == '_' {
+                    1
+                } else if c.is_whitespace() {
+                    2
+                } else {
+                    0
+                }
+            };
+            let mut tokens = Vec::new();
+            let mut start = 0;
+            let mut last_class = None;
+            for (i, c) in s.char_indices() {
+                let c_class = class(c);
+                if i > start && (c_class == 0 || last_class != Some(c_class)) {
+                    tokens.push(&s[start..i]);
+                    start = i;
+                }
+                last_class = Some(c_class);
+            }
+            if start < s.len() {
+                tokens.push(&s[start..]);
+            }
+            tokens
+        }
+        Granularity::Line => s.split_inclusive('\n').collect(),
+    }
+}
+
+// The furthest reaching x coordinates of the diagonals k in [-d, d].
+struct V {
+    offset: isize,
+    v: Vec<usize>,
+}
+
+impl V {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize,
+            v: vec![0; 2 * max_d],
+        }
+    }
+}
+
+impl Index<isize> for V {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl IndexMut<isize> for V {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+// The maximum number of iterations needed to find the middle snake.
+fn max_d(n: usize, m: usize) -> usize {
+    (n + m).div_ceil(2) + 1
+}
+
+fn common_prefix_len(old: &[&str], new: &[&str]) -> usize {
+    old.iter().zip(new).take_while(|(a, b)| a == b).count()
+}
+
+fn common_suffix_len(old: &[&str], new: &[&str]) -> usize {
+    old.iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+// Finds a point (x, y) on an optimal path through the edit graph of `old` and
+// `new` that splits it into two halves with about the same number of edits.
+// Both slices must not be empty and must neither share a common prefix nor a
+// common suffix.
+fn find_middle_snake(old: &[&str], new: &[&str], vf: &mut V, vb: &mut V) -> Option<(usize, usize)> {
+    let n = old.len();
+    let m = new.len();
+    // The length of an optimal edit script is odd iff delta is odd.
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+    vf[1] = 0;
+    vb[1] = 0;
+
+    for d in 0..max_d(n, m) as isize {
+        // Extend the forward paths from the start of the edit graph.
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                vf[k + 1]
+            } else {
+                vf[k - 1] + 1
+            };
+            let y = (x as isize - k) as usize;
+            let (x0, y0) = (x, y);
+            if x < n && y < m {
+                x += common_prefix_len(&old[x..], &new[y..]);
+            }
+            vf[k] = x;
+            if odd && (k - delta).abs() < d && vf[k] + vb[-(k - delta)] >= n {
+                return Some((x0, y0));
+            }
+        }
+        // Extend the backward paths from the end of the edit graph.
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                vb[k + 1]
+            } else {
+                vb[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+            if x < n && y < m {
+                let advance = common_suffix_len(&old[..n - x], &new[..m - y]);
+                x += advance;
+                y += advance;
+            }
+            vb[k] = x;
+            if !odd && (k - delta).abs() <= d && vb[k] + vf[-(k - delta)] >= n {
+                return Some((n - x, m - y));
+            }
+        }
+    }
+    None
+}
+
+// Appends the operations that turn `old` into `new` to `o`.
+fn conquer(old: &[&str], new: &[&str], vf: &mut V, vb: &mut V, o: &mut OperationSeq) {
+    let prefix = common_prefix_len(old, new);
+    for token in &old[..prefix] {
+        o.retain(num_chars(token.as_bytes()) as u64);
+    }
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = common_suffix_len(old, new);
+    let (old, new, suffix) = (
+        &old[..old.len() - suffix],
+        &new[..new.len() - suffix],
+        &old[old.len() - suffix..],
+    );
+
+    let snake = if old.is_empty() || new.is_empty() {
+        None
+    } else {
+        find_middle_snake(old, new, vf, vb)
+    };
+    match snake {
+        // Only split if both halves are smaller to guarantee termination.
+        Some((x, y)) if (x, y) != (0, 0) && (x, y) != (old.len(), new.len()) => {
+            conquer(&old[..x], &new[..y], vf, vb, o);
+            conquer(&old[x..], &new[y..], vf, vb, o);
+        }
+        _ => {
+            for token in old {
+                o.delete(num_chars(token.as_bytes()) as u64);
+            }
+            for token in new {
+                o.insert(token);
+            }
+        }
+    }
+
+    for token in suffix {
+        o.retain(num_chars(token.as_bytes()) as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utilities::Rng, Operation};
+
+    // Returns the number of deleted and inserted characters.
+    fn edits(o: &OperationSeq) -> usize {
+        o.ops()
+            .iter()
+            .map(|op| match op {
+                Operation::Delete(n) => *n as usize,
+                Operation::Insert(s) => num_chars(s.as_bytes()),
+                Operation::Retain(_) => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn tokenize() {
+        assert_eq!(
+            super::tokenize("añ b", Granularity::Char),
+            vec!["a", "ñ", " ", "b"]
+        );
+        assert_eq!(
+            super::tokenize("foo_1  bar,.baz ", Granularity::Word),
+            vec!["foo_1", "  ", "bar", ",", ".", "baz", " "]
+        );
+        assert_eq!(
+            super::tokenize("a\n\nb", Granularity::Line),
+            vec!["a\n", "\n", "b"]
+        );
+        assert!(super::tokenize("", Granularity::Word).is_empty());
+    }
+
+    #[test]
+    fn from_diff() {
+        let o = OperationSeq::from_diff("abc", "abxc");
+        let mut o_exp = OperationSeq::default();
+        o_exp.retain(2);
+        o_exp.insert("x");
+        o_exp.retain(1);
+        assert_eq!(o, o_exp);
+
+        let o = OperationSeq::from_diff("abc", "abc");
+        assert!(o.is_noop());
+        assert_eq!(o.base_len(), 3);
+
+        let o = OperationSeq::from_diff_with("one two", "one three", Granularity::Word);
+        let mut o_exp = OperationSeq::default();
+        o_exp.retain(4);
+        o_exp.delete(3);
+        o_exp.insert("three");
+        assert_eq!(o, o_exp);
+    }
+
+    #[test]
+    fn minimal() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let after_o = o.apply(&s).unwrap();
+            let diff = OperationSeq::from_diff(&s, &after_o);
+            assert_eq!(diff.apply(&s).unwrap(), after_o);
+            assert!(edits(&diff) <= edits(&o));
+        }
+    }
+
+    #[test]
+    fn granularity() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            // Use a small alphabet to get many matching units.
+            let old: String = rng
+                .gen_string(40)
+                .chars()
+                .map(|c| ['a', 'b', ' ', '\n', ','][c as usize % 5])
+                .collect();
+            let new: String = rng
+                .gen_string(40)
+                .chars()
+                .map(|c| ['a', 'b', ' ', '\n', ','][c as usize % 5])
+                .collect();
+            for granularity in &[Granularity::Char, Granularity::Word, Granularity::Line] {
+                let o = OperationSeq::from_diff_with(&old, &new, *granularity);
+                assert_eq!(o.apply(&old).unwrap(), new);
+            }
+        }
+    }
+}
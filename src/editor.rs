@@ -0,0 +1,521 @@
+//! Content changes of editors like Monaco and of the Language Server
+//! Protocol.
+//!
+//! Editors describe edits as ranges of lines and columns which are replaced
+//! with text. Columns are counted in UTF-16 code units and lines are
+//! separated by `\n`, `\r\n` or `\r`. No position lies between the `\r` and the
+//! `\n` of a line break, so operations editing there cannot be converted
+//! into changes. The changes of a Monaco content change event
+//! all refer to the document before the event, while LSP applies its changes
+//! one after another. Changes created from an operation are ordered from the
+//! end to the start of the document, which makes them valid for both.
+//!
+//! ```rust
+//! use operational_transform::{
+//!     editor::{ContentChange, Position, Range},
+//!     OperationSeq,
+//! };
+//!
+//! let text = "hello\nworld";
+//! let changes = vec![
+//!     ContentChange {
+//!         range: Some(Range::new(Position::new(0, 0), Position::new(0, 5))),
+//!         text: "hi".to_owned(),
+//!     },
+//!     ContentChange {
+//!         range: Some(Range::new(Position::new(1, 0), Position::new(1, 0))),
+//!         text: "big ".to_owned(),
+//!     },
+//! ];
+//! let o = OperationSeq::from_lsp_changes(text, &changes).unwrap();
+//! assert_eq!(o.apply(text).unwrap(), "hi\nbig world");
+//!
+//! let changes = o.to_lsp_changes(text).unwrap();
+//! assert_eq!(changes[0].range, Some(Range::new(Position::new(1, 0), Position::new(1, 0))));
+//! assert_eq!(changes[1].range, Some(Range::new(Position::new(0, 0), Position::new(0, 5))));
+//! ```
+
+use crate::{
+    sequence::Sequence, Chars, GenericOperation, OTError, OperationSeq, Utf16, Utf16OperationSeq,
+};
+
+/// A zero-based position in a text. The character is counted in UTF-16 code
+/// units and moved to the end of the line if it is beyond it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    /// The line of the position.
+    pub line: usize,
+    /// The offset of the position in the line.
+    pub character: usize,
+}
+
+impl Position {
+    /// Creates the position at `character` in `line`.
+    #[inline]
+    pub fn new(line: usize, character: usize) -> Self {
+        Self { line, character }
+    }
+}
+
+/// A range of text between two positions, like the `Range` of LSP.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Range {
+    /// The start of the range.
+    pub start: Position,
+    /// The end of the range, which is not part of it.
+    pub end: Position,
+}
+
+impl Range {
+    /// Creates the range from `start` to `end`.
+    #[inline]
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+}
+
+/// A change of a text document, like the `TextDocumentContentChangeEvent` of
+/// LSP.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentChange {
+    /// The range that is replaced or `None` to replace the whole document.
+    pub range: Option<Range>,
+    /// The new text of the range.
+    pub text: String,
+}
+
+/// A range of text with one-based lines and columns, like the `IRange` of
+/// Monaco.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MonacoRange {
+    /// The line of the start of the range.
+    pub start_line_number: usize,
+    /// The column of the start of the range.
+    pub start_column: usize,
+    /// The line of the end of the range.
+    pub end_line_number: usize,
+    /// The column of the end of the range.
+    pub end_column: usize,
+}
+
+/// A change of a Monaco model, like `IModelContentChange`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelContentChange {
+    /// The range that is replaced.
+    pub range: MonacoRange,
+    /// The offset of the range in UTF-16 code units.
+    pub range_offset: usize,
+    /// The length of the range in UTF-16 code units.
+    pub range_length: usize,
+    /// The new text of the range.
+    pub text: String,
+}
+
+impl MonacoRange {
+    fn start(&self) -> Position {
+        Position::new(
+            self.start_line_number.saturating_sub(1),
+            self.start_column.saturating_sub(1),
+        )
+    }
+
+    fn end(&self) -> Position {
+        Position::new(
+            self.end_line_number.saturating_sub(1),
+            self.end_column.saturating_sub(1),
+        )
+    }
+}
+
+// The lines of a text to map positions to offsets and back.
+struct LineIndex {
+    // The UTF-16 offsets of the start and the end of every line without its
+    // line break.
+    lines: Vec<(usize, usize)>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut lines = Vec::new();
+        let (mut start, mut offset) = (0, 0);
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\n' || c == '\r' {
+                lines.push((start, offset));
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                    offset += 1;
+                }
+                start = offset + 1;
+            }
+            offset += c.len_utf16();
+        }
+        lines.push((start, offset));
+        Self { lines }
+    }
+
+    fn offset(&self, pos: Position) -> Result<usize, OTError> {
+        match self.lines.get(pos.line) {
+            Some((start, end)) => Ok(std::cmp::min(start + pos.character, *end)),
+            None => Err(OTError::InvalidPosition {
+                line: pos.line,
+                character: pos.character,
+            }),
+        }
+    }
+
+    // Returns the position of `offset`, or `None` if it is between the `\r`
+    // and the `\n` of a line break, which no position refers to.
+    fn position(&self, offset: usize) -> Option<Position> {
+        let line = self.lines.partition_point(|(start, _)| *start <= offset) - 1;
+        let (start, end) = self.lines[line];
+        if offset > end {
+            return None;
+        }
+        Some(Position::new(line, offset - start))
+    }
+
+    // Returns the positions of the start and the end of `range` in `text`.
+    fn positions(
+        &self,
+        text: &str,
+        range: &std::ops::Range<usize>,
+    ) -> Result<(Position, Position), OTError> {
+        let position = |offset| {
+            self.position(offset)
+                .ok_or_else(|| OTError::InsideLineBreak {
+                    // The offset is counted in characters like the operation.
+                    offset: Utf16::split_at(text, offset)
+                        .map_or(offset, |(head, _)| Chars::len(head)),
+                })
+        };
+        Ok((position(range.start)?, position(range.end)?))
+    }
+
+    // Returns the offsets of the range from `start` to `end`, which are
+    // swapped if `end` is in front of `start`.
+    fn range(&self, start: Position, end: Position) -> Result<std::ops::Range<usize>, OTError> {
+        let (start, end) = (self.offset(start)?, self.offset(end)?);
+        Ok(std::cmp::min(start, end)..std::cmp::max(start, end))
+    }
+}
+
+// Creates the operation replacing `range` of a text of length `len` with `s`.
+fn replace(len: usize, range: std::ops::Range<usize>, s: &str) -> Utf16OperationSeq {
+    let mut o = Utf16OperationSeq::with_capacity(4);
+    o.retain(range.start as u64);
+    o.insert(s);
+    o.delete(range.len() as u64);
+    o.retain((len - range.end) as u64);
+    o
+}
+
+impl OperationSeq {
+    /// Creates an operation on `text` from the LSP `changes`, which are
+    /// applied one after another.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if a change refers to a line that does not exist.
+    pub fn from_lsp_changes(text: &str, changes: &[ContentChange]) -> Result<Self, OTError> {
+        let mut doc = text.to_owned();
+        let mut o = Utf16OperationSeq::default();
+        o.retain(Utf16::len(text) as u64);
+        for change in changes {
+            let len = Utf16::len(&doc);
+            let range = match change.range {
+                Some(range) => LineIndex::new(&doc).range(range.start, range.end)?,
+                None => 0..len,
+            };
+            let edit = replace(len, range, &change.text);
+            doc = edit.apply(&doc)?;
+            o = o.compose(&edit)?;
+        }
+        o.convert(text)
+    }
+
+    /// Creates an operation on `text` from the Monaco `changes`, which all
+    /// refer to `text`. Only the ranges of the changes are used, their
+    /// offsets and lengths are ignored.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if a change refers to a line that does not exist.
+    pub fn from_monaco_changes(
+        text: &str,
+        changes: &[ModelContentChange],
+    ) -> Result<Self, OTError> {
+        let index = LineIndex::new(text);
+        let mut edits = changes
+            .iter()
+            .map(|change| {
+                let range = index.range(change.range.start(), change.range.end())?;
+                Ok((range, change.text.as_str()))
+            })
+            .collect::<Result<Vec<_>, OTError>>()?;
+        // Changes at the same offset are inserted in their given order.
+        edits.sort_by_key(|(range, _)| range.start);
+
+        let mut doc = text.to_owned();
+        let mut o = Utf16OperationSeq::default();
+        o.retain(Utf16::len(text) as u64);
+        // Applying the changes from back to front keeps the offsets of the
+        // remaining changes valid.
+        for (range, s) in edits.into_iter().rev() {
+            let len = Utf16::len(&doc);
+            let range = std::cmp::min(range.start, len)..std::cmp::min(range.end, len);
+            let edit = replace(len, range, s);
+            doc = edit.apply(&doc)?;
+            o = o.compose(&edit)?;
+        }
+        o.convert(text)
+    }
+
+    /// Converts the operation on `text` into LSP changes.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `text` does not have the base length of the
+    /// operation or if the operation edits `text` between the `\r` and the
+    /// `\n` of a line break.
+    pub fn to_lsp_changes(&self, text: &str) -> Result<Vec<ContentChange>, OTError> {
+        let index = LineIndex::new(text);
+        self.edits(text)?
+            .into_iter()
+            .map(|(range, new_text)| {
+                let (start, end) = index.positions(text, &range)?;
+                Ok(ContentChange {
+                    range: Some(Range::new(start, end)),
+                    text: new_text,
+                })
+            })
+            .collect()
+    }
+
+    /// Converts the operation on `text` into Monaco changes.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `text` does not have the base length of the
+    /// operation or if the operation edits `text` between the `\r` and the
+    /// `\n` of a line break.
+    pub fn to_monaco_changes(&self, text: &str) -> Result<Vec<ModelContentChange>, OTError> {
+        let index = LineIndex::new(text);
+        self.edits(text)?
+            .into_iter()
+            .map(|(range, new_text)| {
+                let (start, end) = index.positions(text, &range)?;
+                Ok(ModelContentChange {
+                    range: MonacoRange {
+                        start_line_number: start.line + 1,
+                        start_column: start.character + 1,
+                        end_line_number: end.line + 1,
+                        end_column: end.character + 1,
+                    },
+                    range_offset: range.start,
+                    range_length: range.len(),
+                    text: new_text,
+                })
+            })
+            .collect()
+    }
+
+    // Returns the UTF-16 ranges of `text` replaced by the operation together
+    // with their new text, from the end to the start of `text`.
+    fn edits(&self, text: &str) -> Result<Vec<(std::ops::Range<usize>, String)>, OTError> {
+        let o: Utf16OperationSeq = self.convert(text)?;
+        let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+        let mut pos = 0;
+        for op in o.ops() {
+            match op {
+                GenericOperation::Retain(n) => pos += *n as usize,
+                GenericOperation::Delete(n) => {
+                    let n = *n as usize;
+                    match edits.last_mut() {
+                        Some((range, _)) if range.end == pos => range.end += n,
+                        _ => edits.push((pos..pos + n, String::new())),
+                    }
+                    pos += n;
+                }
+                GenericOperation::Insert(s) => match edits.last_mut() {
+                    Some((range, edit_text)) if range.end == pos => edit_text.push_str(s),
+                    _ => edits.push((pos..pos, s.as_slice().to_owned())),
+                },
+            }
+        }
+        edits.reverse();
+        Ok(edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+
+    fn change(start: (usize, usize), end: (usize, usize), text: &str) -> ContentChange {
+        ContentChange {
+            range: Some(Range::new(
+                Position::new(start.0, start.1),
+                Position::new(end.0, end.1),
+            )),
+            text: text.to_owned(),
+        }
+    }
+
+    fn monaco_change(start: (usize, usize), end: (usize, usize), text: &str) -> ModelContentChange {
+        ModelContentChange {
+            range: MonacoRange {
+                start_line_number: start.0,
+                start_column: start.1,
+                end_line_number: end.0,
+                end_column: end.1,
+            },
+            range_offset: 0,
+            range_length: 0,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn lsp() {
+        let text = "a😀b\r\ncd\n";
+        let changes = [
+            // Characters beyond the line end at the line break.
+            change((0, 3), (0, 10), "X"),
+            change((1, 1), (2, 0), ""),
+            ContentChange {
+                range: None,
+                text: "new".to_owned(),
+            },
+            change((0, 3), (0, 2), "!"),
+        ];
+        let expected = ["a😀X\r\ncd\n", "a😀X\r\nc", "new", "ne!"];
+        for i in 0..changes.len() {
+            let o = OperationSeq::from_lsp_changes(text, &changes[..=i]).unwrap();
+            assert_eq!(o.apply(text).unwrap(), expected[i]);
+        }
+
+        assert_eq!(
+            OperationSeq::from_lsp_changes(text, &[change((3, 0), (3, 0), "")]),
+            Err(OTError::InvalidPosition {
+                line: 3,
+                character: 0
+            })
+        );
+        assert!(OperationSeq::from_lsp_changes(text, &[]).unwrap().is_noop());
+    }
+
+    #[test]
+    fn monaco() {
+        let text = "abc\ndef";
+        // All changes refer to the original text, in any order.
+        let changes = [
+            monaco_change((2, 1), (2, 4), "Y"),
+            monaco_change((1, 1), (1, 2), "X"),
+            monaco_change((1, 4), (1, 4), "1"),
+            monaco_change((1, 4), (1, 4), "2"),
+        ];
+        let o = OperationSeq::from_monaco_changes(text, &changes).unwrap();
+        assert_eq!(o.apply(text).unwrap(), "Xbc12\nY");
+
+        let changes = o.to_monaco_changes(text).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].range_offset, 4);
+        assert_eq!(changes[0].range_length, 3);
+        assert_eq!(
+            changes[0].range,
+            MonacoRange {
+                start_line_number: 2,
+                start_column: 1,
+                end_line_number: 2,
+                end_column: 4
+            }
+        );
+        assert_eq!(
+            OperationSeq::from_monaco_changes(text, &changes).unwrap(),
+            o
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = [rng.gen_string(10), rng.gen_string(10), rng.gen_string(10)].join("\n");
+            let o = rng.gen_operation_seq(&s);
+            let changes = o.to_lsp_changes(&s).unwrap();
+            assert_eq!(OperationSeq::from_lsp_changes(&s, &changes).unwrap(), o);
+            let changes = o.to_monaco_changes(&s).unwrap();
+            assert_eq!(OperationSeq::from_monaco_changes(&s, &changes).unwrap(), o);
+        }
+    }
+
+    #[test]
+    fn cr() {
+        // A `\r` without `\n` is a line break of its own.
+        let text = "a\rb\r\nc";
+        let o = OperationSeq::from_lsp_changes(text, &[change((1, 0), (1, 1), "X")]).unwrap();
+        assert_eq!(o.apply(text).unwrap(), "a\rX\r\nc");
+        assert_eq!(
+            o.to_lsp_changes(text).unwrap(),
+            vec![change((1, 0), (1, 1), "X")]
+        );
+        let o = OperationSeq::from_lsp_changes(text, &[change((2, 0), (2, 9), "Y")]).unwrap();
+        assert_eq!(o.apply(text).unwrap(), "a\rb\r\nY");
+        let o = OperationSeq::from_lsp_changes(text, &[change((0, 9), (1, 0), "")]).unwrap();
+        assert_eq!(o.apply(text).unwrap(), "ab\r\nc");
+    }
+
+    #[test]
+    fn crlf() {
+        let text = "ab\r\ncd";
+        // Offsets on both sides of a `\r\n` line break round-trip.
+        for &(offset, position) in &[(2, (0, 2)), (4, (1, 0))] {
+            let mut o = OperationSeq::default();
+            o.retain(offset);
+            o.insert("X");
+            o.retain(6 - offset);
+            let changes = o.to_lsp_changes(text).unwrap();
+            assert_eq!(changes, vec![change(position, position, "X")]);
+            assert_eq!(OperationSeq::from_lsp_changes(text, &changes).unwrap(), o);
+            let changes = o.to_monaco_changes(text).unwrap();
+            assert_eq!(
+                OperationSeq::from_monaco_changes(text, &changes).unwrap(),
+                o
+            );
+        }
+
+        // No position refers to the offset between `\r` and `\n`.
+        let mut o = OperationSeq::default();
+        o.retain(3);
+        o.insert("X");
+        o.retain(3);
+        assert_eq!(o.apply(text).unwrap(), "ab\rX\ncd");
+        let err = OTError::InsideLineBreak { offset: 3 };
+        assert_eq!(o.to_lsp_changes(text), Err(err.clone()));
+        assert_eq!(o.to_monaco_changes(text), Err(err));
+        let mut o = OperationSeq::default();
+        o.retain(1);
+        o.delete(1);
+        o.retain(3);
+        assert_eq!(
+            o.to_lsp_changes("😀\r\ncd"),
+            Err(OTError::InsideLineBreak { offset: 2 })
+        );
+
+        // Line breaks are only replaced as a whole.
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = [rng.gen_string(5), rng.gen_string(5), rng.gen_string(5)].join("\r\n");
+            let o = rng.gen_operation_seq(&s);
+            match o.to_lsp_changes(&s) {
+                Ok(changes) => {
+                    assert_eq!(OperationSeq::from_lsp_changes(&s, &changes).unwrap(), o)
+                }
+                Err(OTError::InsideLineBreak { .. }) => {}
+                Err(err) => panic!("{:?}", err),
+            }
+        }
+    }
+}
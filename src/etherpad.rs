@@ -0,0 +1,411 @@
+//! Etherpad's changeset format.
+//!
+//! A changeset like `Z:6>6=5|1+1+5$\nworld` consists of
+//!
+//! - the header `Z:` with the old length of the document, `>` or `<` and the
+//!   difference to the new length
+//! - the operations `=n` (keep), `-n` (delete) and `+n` (insert), which may
+//!   be preceded by attributes `*a` and the number of newlines `|l` they
+//!   cover
+//! - `$` followed by the text of all inserts
+//!
+//! All numbers are written in base 36 and count UTF-16 code units. Keeps at
+//! the end of the document are left out. Only the plain text is kept when a
+//! changeset is parsed, so formatting and authorship attributes are dropped.
+//!
+//! ```rust
+//! use operational_transform::OperationSeq;
+//!
+//! let doc = "hello\n";
+//! let o = OperationSeq::from_etherpad("Z:6>6=5|1+1+5$\nworld", doc).unwrap();
+//! assert_eq!(o.apply(doc).unwrap(), "hello\nworld\n");
+//! assert_eq!(o.to_etherpad(doc).unwrap(), "Z:6>6=5|1+1+5$\nworld");
+//! ```
+
+use crate::{
+    sequence::Sequence, Chars, DecodeErrorKind, OTError, Operation, OperationSeq, Utf16,
+    Utf16OperationSeq,
+};
+use bytecount::num_chars;
+
+impl OperationSeq {
+    /// Parses the Etherpad `changeset` on `doc`.
+    ///
+    /// # Error
+    ///
+    /// Returns `OTError::Decode` with the byte offset of the problem if the
+    /// changeset is malformed, or an `OTError` if it does not fit `doc`.
+    pub fn from_etherpad(changeset: &str, doc: &str) -> Result<Self, OTError> {
+        let mut parser = Parser { changeset, pos: 0 };
+        if !changeset.starts_with("Z:") {
+            return Err(parser.invalid(DecodeErrorKind::Expected("`Z:`")));
+        }
+        parser.pos += 2;
+        let old_len = parser.number()?;
+        let grows = match parser.peek() {
+            Some('>') => true,
+            Some('<') => false,
+            _ => return Err(parser.invalid(DecodeErrorKind::Expected("`>` or `<`"))),
+        };
+        parser.pos += 1;
+        let diff_start = parser.pos;
+        let diff = parser.number()?;
+        let new_len = if grows {
+            old_len.checked_add(diff)
+        } else {
+            old_len.checked_sub(diff)
+        };
+        let new_len = new_len.ok_or_else(|| parser.invalid(DecodeErrorKind::CountOverflow))?;
+        if old_len != Utf16::len(doc) {
+            return Err(OTError::BaseLengthMismatch {
+                expected: old_len,
+                actual: Utf16::len(doc),
+            });
+        }
+
+        let bank_start = match changeset[parser.pos..].find('$') {
+            Some(i) => parser.pos + i,
+            None => return Err(parser.invalid(DecodeErrorKind::MissingCharBank)),
+        };
+        let mut bank = &changeset[bank_start + 1..];
+        let mut rest = doc;
+        let mut o = Utf16OperationSeq::default();
+        while parser.pos < bank_start {
+            let op_start = parser.pos;
+            let mut lines = 0;
+            loop {
+                match parser.peek() {
+                    // The attributes are dropped.
+                    Some('*') => {
+                        parser.pos += 1;
+                        parser.number()?;
+                    }
+                    Some('|') => {
+                        parser.pos += 1;
+                        lines = parser.number()?;
+                    }
+                    _ => break,
+                }
+            }
+            let opcode = match parser.peek() {
+                Some(c) if c == '=' || c == '-' || c == '+' => c,
+                _ => return Err(parser.invalid(DecodeErrorKind::Expected("`=`, `-` or `+`"))),
+            };
+            parser.pos += 1;
+            let n = parser.number()?;
+            let text = if opcode == '+' { &mut bank } else { &mut rest };
+            let (s, tail) = Utf16::split_at(text, n)
+                .ok_or_else(|| invalid(DecodeErrorKind::SplitsCharacter, op_start))?;
+            *text = tail;
+            if Utf16::len(s) != n {
+                return Err(invalid(DecodeErrorKind::TextMismatch, op_start));
+            }
+            if s.matches('\n').count() != lines || (lines > 0 && !s.ends_with('\n')) {
+                return Err(invalid(DecodeErrorKind::NewlineMismatch, op_start));
+            }
+            match opcode {
+                '=' => o.retain(n as u64),
+                '-' => o.delete(n as u64),
+                _ => o.insert(s),
+            }
+        }
+        if !bank.is_empty() {
+            return Err(invalid(
+                DecodeErrorKind::UnusedText,
+                changeset.len() - bank.len(),
+            ));
+        }
+        o.retain(Utf16::len(rest) as u64);
+        if o.target_len() != new_len {
+            return Err(invalid(DecodeErrorKind::NewLengthMismatch, diff_start));
+        }
+        o.convert(doc)
+    }
+
+    /// Converts the operation on `doc` into an Etherpad changeset. Like
+    /// Etherpad does, deletes are put in front of inserts at the same
+    /// position.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `doc` does not have the base length of the
+    /// operation.
+    pub fn to_etherpad(&self, doc: &str) -> Result<String, OTError> {
+        let doc_len = num_chars(doc.as_bytes());
+        if self.base_len != doc_len {
+            return Err(OTError::BaseLengthMismatch {
+                expected: self.base_len,
+                actual: doc_len,
+            });
+        }
+        // The retains and deletes add up to the length of `doc`, so `rest`
+        // always holds the characters they cover.
+        let mismatch = || OTError::BaseLengthMismatch {
+            expected: self.base_len,
+            actual: doc_len,
+        };
+        let old_len = Utf16::len(doc);
+        let mut new_len = old_len;
+        let mut ops = String::new();
+        let mut bank = String::new();
+        // The kept text and the inserted text that are not written yet.
+        let mut keeps = String::new();
+        let mut inserts = String::new();
+        let mut rest = doc;
+        for op in &self.ops {
+            match op {
+                Operation::Retain(n) => {
+                    write_op(&mut ops, '+', &inserts);
+                    bank.push_str(&inserts);
+                    inserts.clear();
+                    let (head, tail) = Chars::split_at(rest, *n as usize).ok_or_else(mismatch)?;
+                    keeps.push_str(head);
+                    rest = tail;
+                }
+                Operation::Delete(n) => {
+                    write_op(&mut ops, '=', &keeps);
+                    keeps.clear();
+                    let (head, tail) = Chars::split_at(rest, *n as usize).ok_or_else(mismatch)?;
+                    write_op(&mut ops, '-', head);
+                    new_len -= Utf16::len(head);
+                    rest = tail;
+                }
+                Operation::Insert(s) => {
+                    write_op(&mut ops, '=', &keeps);
+                    keeps.clear();
+                    inserts.push_str(s);
+                    new_len += Utf16::len(s);
+                }
+            }
+        }
+        // Keeps at the end are left out.
+        write_op(&mut ops, '+', &inserts);
+        bank.push_str(&inserts);
+
+        let (sign, diff) = if new_len >= old_len {
+            ('>', new_len - old_len)
+        } else {
+            ('<', old_len - new_len)
+        };
+        Ok(format!(
+            "Z:{}{}{}{}${}",
+            base36(old_len),
+            sign,
+            base36(diff),
+            ops,
+            bank
+        ))
+    }
+
+    /// Migrates the Etherpad history `changesets` of a pad starting with
+    /// `text` into operations. The initial text of a pad is usually `"\n"`.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if a changeset is malformed or does not fit the
+    /// document it is applied to.
+    pub fn from_etherpad_history<I>(text: &str, changesets: I) -> Result<Vec<Self>, OTError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut doc = text.to_owned();
+        let mut history = Vec::new();
+        for changeset in changesets {
+            let o = Self::from_etherpad(changeset.as_ref(), &doc)?;
+            doc = o.apply(&doc)?;
+            history.push(o);
+        }
+        Ok(history)
+    }
+}
+
+// Reads the operations of a changeset.
+struct Parser<'a> {
+    changeset: &'a str,
+    // The byte position in the changeset.
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.changeset[self.pos..].chars().next()
+    }
+
+    // Reads a number in base 36.
+    fn number(&mut self) -> Result<usize, OTError> {
+        let digits = self.changeset[self.pos..]
+            .find(|c: char| !c.is_ascii_digit() && !c.is_ascii_lowercase())
+            .map_or(&self.changeset[self.pos..], |i| {
+                &self.changeset[self.pos..self.pos + i]
+            });
+        if digits.is_empty() {
+            return Err(self.invalid(DecodeErrorKind::Expected("a number")));
+        }
+        // All digits are valid, so parsing fails only if the number is too
+        // large.
+        let n = usize::from_str_radix(digits, 36)
+            .map_err(|_| self.invalid(DecodeErrorKind::CountOverflow))?;
+        self.pos += digits.len();
+        Ok(n)
+    }
+
+    fn invalid(&self, kind: DecodeErrorKind) -> OTError {
+        invalid(kind, self.pos)
+    }
+}
+
+// Creates the error for a malformed changeset at `offset`.
+fn invalid(kind: DecodeErrorKind, offset: usize) -> OTError {
+    OTError::Decode { kind, offset }
+}
+
+// Appends the operation with `opcode` covering `s` to `ops`. Following
+// Etherpad, the part up to the last newline is written separately with the
+// number of newlines it covers.
+fn write_op(ops: &mut String, opcode: char, s: &str) {
+    let (lines, rest) = match s.rfind('\n') {
+        Some(i) => s.split_at(i + 1),
+        None => ("", s),
+    };
+    if !lines.is_empty() {
+        ops.push('|');
+        ops.push_str(&base36(lines.matches('\n').count()));
+        ops.push(opcode);
+        ops.push_str(&base36(Utf16::len(lines)));
+    }
+    if !rest.is_empty() {
+        ops.push(opcode);
+        ops.push_str(&base36(Utf16::len(rest)));
+    }
+}
+
+fn base36(mut n: usize) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((n % 36) as u32, 36).unwrap());
+        n /= 36;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+    use rand::{prelude::*, Rng as _};
+
+    // Changesets as Etherpad writes them with the document before and after.
+    const FIXTURES: &[(&str, &str, &str)] = &[
+        ("Z:1>5+5$hello", "\n", "hello\n"),
+        ("Z:6>6=5|1+1+5$\nworld", "hello\n", "hello\nworld\n"),
+        ("Z:4<2|1-2$", "a\nb\n", "b\n"),
+        ("Z:4>1|1=2=1+1$x", "a\nb\n", "a\nbx\n"),
+        ("Z:4>0=1-1+1$X", "abc\n", "aXc\n"),
+        ("Z:1>2+2$😀", "\n", "😀\n"),
+        ("Z:a<9|2-9$", "ab\ncdefg\n\n", "\n"),
+        (
+            "Z:1>z+z$abcdefghijklmnopqrstuvwxyzabcdefghi",
+            "\n",
+            "abcdefghijklmnopqrstuvwxyzabcdefghi\n",
+        ),
+    ];
+
+    #[test]
+    fn fixtures() {
+        for (changeset, before, after) in FIXTURES {
+            let o = OperationSeq::from_etherpad(changeset, before).unwrap();
+            assert_eq!(o.apply(before).unwrap(), *after);
+            assert_eq!(o.to_etherpad(before).unwrap(), *changeset);
+        }
+
+        // Attributes and keeps at the end are dropped.
+        let o = OperationSeq::from_etherpad("Z:2>3=1*0*1+3|1=1$abc", "x\n").unwrap();
+        assert_eq!(o.apply("x\n").unwrap(), "xabc\n");
+        assert_eq!(o.to_etherpad("x\n").unwrap(), "Z:2>3=1+3$abc");
+    }
+
+    #[test]
+    fn malformed() {
+        let invalid_inputs = [
+            ("X:1>0$", DecodeErrorKind::Expected("`Z:`"), 0),
+            ("Z:1!0$", DecodeErrorKind::Expected("`>` or `<`"), 3),
+            ("Z:1>0", DecodeErrorKind::MissingCharBank, 5),
+            ("Z:1>1+$a", DecodeErrorKind::Expected("a number"), 6),
+            ("Z:1>1#1$a", DecodeErrorKind::Expected("`=`, `-` or `+`"), 5),
+            ("Z:1>1é1$a", DecodeErrorKind::Expected("`=`, `-` or `+`"), 5),
+            ("Z:1>1+2$a", DecodeErrorKind::TextMismatch, 5),
+            ("Z:1<1-2$", DecodeErrorKind::TextMismatch, 5),
+            ("Z:1>1|1+1$a", DecodeErrorKind::NewlineMismatch, 5),
+            ("Z:1>1+1$\n", DecodeErrorKind::NewlineMismatch, 5),
+            ("Z:1>1+1$ab", DecodeErrorKind::UnusedText, 9),
+            ("Z:1>2+1$a", DecodeErrorKind::NewLengthMismatch, 4),
+            ("Z:1>12+1$a", DecodeErrorKind::NewLengthMismatch, 4),
+            ("Z:1<2$", DecodeErrorKind::CountOverflow, 5),
+            ("Z:1>1+zzzzzzzzzzzzzz$a", DecodeErrorKind::CountOverflow, 6),
+        ];
+        for &(changeset, kind, offset) in invalid_inputs.iter() {
+            assert_eq!(
+                OperationSeq::from_etherpad(changeset, "\n"),
+                Err(OTError::Decode { kind, offset }),
+                "{}",
+                changeset
+            );
+        }
+        assert_eq!(
+            OperationSeq::from_etherpad("Z:1>2+1$a", "\n")
+                .unwrap_err()
+                .to_string(),
+            "new length does not match at byte 4"
+        );
+        assert_eq!(
+            OperationSeq::from_etherpad("Z:2<1-1$", "😀"),
+            Err(OTError::Decode {
+                kind: DecodeErrorKind::SplitsCharacter,
+                offset: 5
+            })
+        );
+        // Random input must never panic.
+        let alphabet: Vec<char> = "Z:<>=-+*|$019az\né".chars().collect();
+        let mut rng = StdRng::from_entropy();
+        for _ in 0..10000 {
+            let len = rng.gen_range(0, 20);
+            let mut changeset = "Z:".to_owned();
+            changeset.extend((0..len).map(|_| alphabet.choose(&mut rng).unwrap()));
+            let _ = OperationSeq::from_etherpad(&changeset, "ab\n");
+        }
+
+        assert_eq!(
+            OperationSeq::from_etherpad("Z:2>0$", "\n"),
+            Err(OTError::BaseLengthMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = [rng.gen_string(10), rng.gen_string(10), rng.gen_string(10)].join("\n");
+            let o = rng.gen_operation_seq(&s);
+            let changeset = o.to_etherpad(&s).unwrap();
+            assert_eq!(OperationSeq::from_etherpad(&changeset, &s).unwrap(), o);
+        }
+    }
+
+    #[test]
+    fn history() {
+        let changesets = ["Z:1>5+5$hello", "Z:6>6=5|1+1+5$\nworld", "Z:c<6|1-6$"];
+        let history = OperationSeq::from_etherpad_history("\n", &changesets).unwrap();
+        assert_eq!(history.len(), 3);
+        let o = OperationSeq::compose_all(history).unwrap().unwrap();
+        assert_eq!(o.apply("\n").unwrap(), "world\n");
+
+        assert!(OperationSeq::from_etherpad_history("\n", &["Z:1>1+1$a", "Z:1>0$"]).is_err());
+    }
+}
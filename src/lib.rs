@@ -59,10 +59,34 @@
 //! let mut o = OperationSeq::default();
 //! o.retain(3);
 //! o.insert("def");
-//! let p = o.invert(s);
+//! let p = o.invert(s).unwrap();
 //! assert_eq!(p.apply(&o.apply(s).unwrap()).unwrap(), s);
 //! ```
 //!
+//! ... operate on other sequences than text, like lists:
+//! ```rust
+//! use operational_transform::ListOperationSeq;
+//!
+//! let mut o = ListOperationSeq::default();
+//! o.retain(1);
+//! o.insert(&[4, 5]);
+//! o.delete(2);
+//! assert_eq!(o.apply(&[1, 2, 3]).unwrap(), vec![1, 4, 5]);
+//! ```
+//!
+//! ... count lengths in UTF-16 code units like JavaScript does:
+//! ```rust
+//! use operational_transform::{OperationSeq, Utf16OperationSeq};
+//!
+//! let mut o = OperationSeq::default();
+//! o.retain(1);
+//! o.insert("!");
+//! let o_utf16: Utf16OperationSeq = o.convert("😀").unwrap();
+//! assert_eq!(o_utf16.base_len(), 2);
+//! assert_eq!(o_utf16.apply("😀").unwrap(), "😀!");
+//! assert_eq!(o_utf16.convert("😀").unwrap(), o);
+//! ```
+//!
 //! ## Features
 //!
 //! Serialization is supported by using the `serde` feature.
@@ -83,45 +107,180 @@
 //! assert_eq!(o, o_exp);
 //! ```
 //!
+//! Formats which are not human readable, like bincode or postcard, store
+//! operations as an enum with the variants `Retain`, `Delete` and `Insert`
+//! instead.
+//!
+//! The `serde` feature also converts operations from and to the wire formats
+//! of ShareDB's `text` and `text-unicode` types in the `sharedb` module and
+//! of CodeMirror's change sets in the `codemirror` module.
+//! Rich text operations are serialized to the JSON of Quill's deltas.
+//!
+//! Large documents can be edited in place as a rope from the
+//! [ropey](https://crates.io/crates/ropey) crate by using the `ropey`
+//! feature.
+//!
+//! Long histories can be composed in parallel with `par_compose_all` by using
+//! the `rayon` feature.
+//!
+//! Lengths can be counted in grapheme clusters with `GraphemeOperationSeq`
+//! by using the `unicode-segmentation` feature.
+//!
 //! ## Acknowledgement
 //! In the current state the code is ported from
 //! [here](https://github.com/Operational-Transformation/ot.js/). It might
 //! change in the future as there is much room for optimisation and also
 //! usability.
 
+pub mod binary;
+pub mod buffer;
+pub mod client;
+#[cfg(feature = "serde")]
+pub mod codemirror;
+pub mod diff;
+pub mod editor;
+pub mod etherpad;
+pub mod rich_text;
+#[cfg(feature = "ropey")]
+pub mod rope;
+pub mod selection;
+pub mod sequence;
 #[cfg(feature = "serde")]
 pub mod serde;
+pub mod server;
+#[cfg(feature = "serde")]
+pub mod sharedb;
+pub mod undo;
+mod walk;
 
-#[cfg(any(test, bench))]
+#[cfg(test)]
 pub mod utilities;
 
-use bytecount::num_chars;
-use std::{cmp::Ordering, error::Error, fmt, iter::FromIterator};
+use selection::Bias;
+#[cfg(feature = "unicode-segmentation")]
+use sequence::Graphemes;
+use sequence::{Bytes, Chars, List, Measured, Sequence, Utf16};
+use std::{error::Error, fmt, iter::FromIterator, mem};
+use walk::{Piece, Sink};
 
-/// A single operation to be executed at the cursor's current position.
-#[derive(Debug, Clone, PartialEq)]
-pub enum Operation {
-    // Deletes n characters at the current cursor position.
+/// A single operation on a sequence to be executed at the cursor's current
+/// position.
+pub enum GenericOperation<S: Sequence> {
+    // Deletes n items at the current cursor position.
     Delete(u64),
     // Moves the cursor n positions forward.
     Retain(u64),
-    // Inserts string at the current cursor position.
-    Insert(String),
+    // Inserts a sequence at the current cursor position.
+    Insert(Measured<S>),
 }
 
-/// A sequence of `Operation`s on text.
-#[derive(Clone, Debug, PartialEq)]
-pub struct OperationSeq {
+/// A single operation on text to be executed at the cursor's current position.
+pub type Operation = GenericOperation<Chars>;
+
+/// A single operation on text with lengths counted in UTF-8 bytes to be
+/// executed at the cursor's current position.
+pub type ByteOperation = GenericOperation<Bytes>;
+
+/// A single operation on text with lengths counted in grapheme clusters to be
+/// executed at the cursor's current position.
+#[cfg(feature = "unicode-segmentation")]
+pub type GraphemeOperation = GenericOperation<Graphemes>;
+
+/// A single operation on text with lengths counted in UTF-16 code units to be
+/// executed at the cursor's current position.
+pub type Utf16Operation = GenericOperation<Utf16>;
+
+/// A single operation on a list of `T` to be executed at the cursor's current
+/// position.
+pub type ListOperation<T> = GenericOperation<List<T>>;
+
+impl<S: Sequence> Clone for GenericOperation<S> {
+    fn clone(&self) -> Self {
+        match self {
+            GenericOperation::Delete(n) => GenericOperation::Delete(*n),
+            GenericOperation::Retain(n) => GenericOperation::Retain(*n),
+            GenericOperation::Insert(s) => GenericOperation::Insert(s.clone()),
+        }
+    }
+}
+
+impl<S: Sequence> fmt::Debug for GenericOperation<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenericOperation::Delete(n) => f.debug_tuple("Delete").field(n).finish(),
+            GenericOperation::Retain(n) => f.debug_tuple("Retain").field(n).finish(),
+            GenericOperation::Insert(s) => f.debug_tuple("Insert").field(s).finish(),
+        }
+    }
+}
+
+impl<S: Sequence> PartialEq for GenericOperation<S> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (GenericOperation::Delete(i), GenericOperation::Delete(j)) => i == j,
+            (GenericOperation::Retain(i), GenericOperation::Retain(j)) => i == j,
+            (GenericOperation::Insert(s), GenericOperation::Insert(t)) => s == t,
+            _ => false,
+        }
+    }
+}
+
+/// A sequence of `GenericOperation`s on a sequence.
+pub struct GenericOperationSeq<S: Sequence> {
     // The consecutive operations to be applied to the target.
-    ops: Vec<Operation>,
-    // The required length of a string these operations can be applied to.
+    ops: Vec<GenericOperation<S>>,
+    // The required length of a sequence these operations can be applied to.
     base_len: usize,
-    // The length of the resulting string after the operations have been
+    // The length of the resulting sequence after the operations have been
     // applied.
     target_len: usize,
 }
 
-impl Default for OperationSeq {
+/// A sequence of `Operation`s on text.
+pub type OperationSeq = GenericOperationSeq<Chars>;
+
+/// A sequence of `ByteOperation`s on text.
+pub type ByteOperationSeq = GenericOperationSeq<Bytes>;
+
+/// A sequence of `GraphemeOperation`s on text.
+#[cfg(feature = "unicode-segmentation")]
+pub type GraphemeOperationSeq = GenericOperationSeq<Graphemes>;
+
+/// A sequence of `Utf16Operation`s on text, as used by JavaScript editors.
+pub type Utf16OperationSeq = GenericOperationSeq<Utf16>;
+
+/// A sequence of `ListOperation`s on a list of `T`.
+pub type ListOperationSeq<T> = GenericOperationSeq<List<T>>;
+
+impl<S: Sequence> Clone for GenericOperationSeq<S> {
+    fn clone(&self) -> Self {
+        Self {
+            ops: self.ops.clone(),
+            base_len: self.base_len,
+            target_len: self.target_len,
+        }
+    }
+}
+
+impl<S: Sequence> fmt::Debug for GenericOperationSeq<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperationSeq")
+            .field("ops", &self.ops)
+            .field("base_len", &self.base_len)
+            .field("target_len", &self.target_len)
+            .finish()
+    }
+}
+
+impl<S: Sequence> PartialEq for GenericOperationSeq<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ops == other.ops
+            && self.base_len == other.base_len
+            && self.target_len == other.target_len
+    }
+}
+
+impl<S: Sequence> Default for GenericOperationSeq<S> {
     fn default() -> Self {
         Self {
             ops: Vec::new(),
@@ -131,9 +290,9 @@ impl Default for OperationSeq {
     }
 }
 
-impl FromIterator<Operation> for OperationSeq {
-    fn from_iter<T: IntoIterator<Item = Operation>>(ops: T) -> Self {
-        let mut operations = OperationSeq::default();
+impl<S: Sequence> FromIterator<GenericOperation<S>> for GenericOperationSeq<S> {
+    fn from_iter<T: IntoIterator<Item = GenericOperation<S>>>(ops: T) -> Self {
+        let mut operations = GenericOperationSeq::default();
         for op in ops {
             operations.add(op);
         }
@@ -141,13 +300,292 @@ impl FromIterator<Operation> for OperationSeq {
     }
 }
 
+impl<'a, S: Sequence> Sink<'a, S, ()> for GenericOperationSeq<S> {
+    #[inline]
+    fn delete(&mut self, n: u64) {
+        GenericOperationSeq::delete(self, n);
+    }
+
+    #[inline]
+    fn retain(&mut self, n: u64, _format: ()) {
+        GenericOperationSeq::retain(self, n);
+    }
+
+    #[inline]
+    fn insert(&mut self, s: &'a S::Slice, len: usize, _format: ()) {
+        self.push_insert(s, len);
+    }
+}
+
+/// The side an insert ends up on if it is transformed against a concurrent
+/// insert at the same position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// The insert ends up in front of the concurrent one.
+    Left,
+    /// The insert ends up behind the concurrent one.
+    Right,
+}
+
+impl Side {
+    /// Orders the inserts of two sites by their ids, so that two peers agree
+    /// on the order without further coordination. The site with the smaller
+    /// id gets the left side.
+    #[inline]
+    pub fn from_ids<T: Ord>(own: T, other: T) -> Self {
+        if own <= other {
+            Side::Left
+        } else {
+            Side::Right
+        }
+    }
+}
+
 /// Error for failed operational transform operations.
-#[derive(Clone, Debug)]
-pub struct OTError;
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OTError {
+    /// The sequence an operation is applied to does not have the base length
+    /// of the operation.
+    BaseLengthMismatch {
+        /// The base length of the operation.
+        expected: usize,
+        /// The length of the sequence.
+        actual: usize,
+    },
+    /// The target length of the first operation of a composition differs
+    /// from the base length of the second one.
+    ComposeLengthMismatch {
+        /// The target length of the first operation.
+        target_len: usize,
+        /// The base length of the second operation.
+        base_len: usize,
+    },
+    /// The base lengths of two concurrent operations differ.
+    TransformLengthMismatch {
+        /// The base length of the first operation.
+        base_len: usize,
+        /// The base length of the second operation.
+        other_base_len: usize,
+    },
+    /// Two operations ran out of sync while they were walked in parallel.
+    OperationMismatch {
+        /// The index of the operation of the first sequence at which the
+        /// walk failed.
+        index: usize,
+    },
+    /// An operation splits a character of a text whose lengths are counted
+    /// in smaller units, e.g. in the middle of a UTF-16 surrogate pair.
+    NotACharBoundary {
+        /// The offset of the split in the units of the operation.
+        offset: usize,
+    },
+    /// An operation is based on a revision the server does not know yet.
+    RevisionMismatch {
+        /// The revision the operation is based on.
+        revision: usize,
+        /// The latest revision of the server.
+        latest: usize,
+    },
+    /// The server acknowledged an operation although none was outstanding.
+    NoPendingOperation,
+    /// A rich text operation was applied to something that is not a document.
+    NotADocument,
+    /// A position of an editor change refers to a line that does not exist.
+    InvalidPosition {
+        /// The zero-based line of the position.
+        line: usize,
+        /// The character of the position in the line.
+        character: usize,
+    },
+    /// An operation edits a text between the `\r` and the `\n` of a line
+    /// break, which no position of an editor refers to.
+    InsideLineBreak {
+        /// The offset between `\r` and `\n` in the units of the operation.
+        offset: usize,
+    },
+    /// A rich text operation carries formatting attributes that a plain text
+    /// operation cannot hold.
+    UnsupportedAttributes {
+        /// The index of the rich text operation with attributes.
+        index: usize,
+    },
+    /// The binary form or an Etherpad changeset of an operation is
+    /// malformed.
+    Decode {
+        /// What is wrong with the input.
+        kind: DecodeErrorKind,
+        /// The byte offset in the input at which the problem was found.
+        offset: usize,
+    },
+    /// The count of a decoded component overflows the lengths of the
+    /// operation.
+    CountOverflow {
+        /// The index of the component.
+        index: usize,
+    },
+    /// The strict deserialization of the `serde` feature found a retain,
+    /// delete or insert of length zero.
+    EmptyComponent {
+        /// The index of the empty operation.
+        index: usize,
+    },
+    /// Input of the strict deserialization of the `serde` feature exceeds one
+    /// of its limits.
+    LimitExceeded {
+        /// The limit that was exceeded.
+        kind: LimitKind,
+        /// The maximum allowed by the limit.
+        max: usize,
+        /// The value of the input, which is larger than `max`.
+        actual: usize,
+        /// The index of the operation at which the limit was exceeded.
+        index: usize,
+    },
+    /// An operation could not be deserialized. With the `serde` feature this
+    /// error implements `serde::de::Error` and can be returned by
+    /// deserializers.
+    Deserialize(String),
+}
 
 impl fmt::Display for OTError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "incompatible lengths")
+        match self {
+            OTError::BaseLengthMismatch { expected, actual } => write!(
+                f,
+                "operation expects a sequence of length {} but got one of length {}",
+                expected, actual
+            ),
+            OTError::ComposeLengthMismatch {
+                target_len,
+                base_len,
+            } => write!(
+                f,
+                "cannot compose an operation with target length {} and one with base length {}",
+                target_len, base_len
+            ),
+            OTError::TransformLengthMismatch {
+                base_len,
+                other_base_len,
+            } => write!(
+                f,
+                "cannot transform operations with base lengths {} and {}",
+                base_len, other_base_len
+            ),
+            OTError::OperationMismatch { index } => {
+                write!(f, "operations do not match at operation {}", index)
+            }
+            OTError::NotACharBoundary { offset } => {
+                write!(f, "offset {} is not on a character boundary", offset)
+            }
+            OTError::RevisionMismatch { revision, latest } => write!(
+                f,
+                "revision {} is ahead of the latest revision {}",
+                revision, latest
+            ),
+            OTError::NoPendingOperation => write!(f, "no operation is awaiting confirmation"),
+            OTError::NotADocument => write!(f, "operation is not a document"),
+            OTError::InvalidPosition { line, character } => write!(
+                f,
+                "position {}:{} is outside of the document",
+                line, character
+            ),
+            OTError::InsideLineBreak { offset } => {
+                write!(f, "offset {} is inside of a line break", offset)
+            }
+            OTError::UnsupportedAttributes { index } => {
+                write!(f, "operation {} carries formatting attributes", index)
+            }
+            OTError::Decode { kind, offset } => write!(f, "{} at byte {}", kind, offset),
+            OTError::CountOverflow { index } => {
+                write!(f, "count of component {} overflows", index)
+            }
+            OTError::EmptyComponent { index } => write!(f, "operation {} is empty", index),
+            OTError::LimitExceeded {
+                kind,
+                max,
+                actual,
+                index,
+            } => write!(
+                f,
+                "{} of {} exceeds the maximum of {} at operation {}",
+                kind, actual, max, index
+            ),
+            OTError::Deserialize(msg) => write!(f, "invalid operation: {}", msg),
+        }
+    }
+}
+
+/// The limit that was exceeded, see [`OTError::LimitExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LimitKind {
+    /// The number of operations.
+    Ops,
+    /// The length of a single insert.
+    InsertLen,
+    /// The base length of the operation.
+    BaseLen,
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LimitKind::Ops => "number of operations",
+            LimitKind::InsertLen => "insert length",
+            LimitKind::BaseLen => "base length",
+        })
+    }
+}
+
+/// The kind of problem of a malformed encoded operation, see
+/// [`OTError::Decode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeErrorKind {
+    /// A varint ends early or is longer than any count.
+    InvalidVarint,
+    /// A count or the lengths of the operation overflow.
+    CountOverflow,
+    /// An insert is longer than the rest of the input.
+    InsertExceedsInput,
+    /// An insert is not valid UTF-8.
+    InvalidUtf8,
+    /// The base length is not in front of all other operations.
+    UnexpectedBaseLength,
+    /// The input does not continue with the described token.
+    Expected(&'static str),
+    /// A changeset lacks the `$` in front of the inserted text.
+    MissingCharBank,
+    /// An operation of a changeset splits a surrogate pair.
+    SplitsCharacter,
+    /// An operation of a changeset covers more text than there is.
+    TextMismatch,
+    /// The newline count of an operation of a changeset is wrong.
+    NewlineMismatch,
+    /// Inserted text of a changeset is left over after all operations.
+    UnusedText,
+    /// The new length in the header of a changeset is not the length after
+    /// the operations.
+    NewLengthMismatch,
+}
+
+impl fmt::Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DecodeErrorKind::InvalidVarint => "truncated or overlong varint",
+            DecodeErrorKind::CountOverflow => "count overflows",
+            DecodeErrorKind::InsertExceedsInput => "insert exceeds input",
+            DecodeErrorKind::InvalidUtf8 => "invalid UTF-8",
+            DecodeErrorKind::UnexpectedBaseLength => "unexpected base length",
+            DecodeErrorKind::Expected(expected) => return write!(f, "expected {}", expected),
+            DecodeErrorKind::MissingCharBank => "missing `$`",
+            DecodeErrorKind::SplitsCharacter => "operation splits a character",
+            DecodeErrorKind::TextMismatch => "operation does not fit the text",
+            DecodeErrorKind::NewlineMismatch => "newline count does not match",
+            DecodeErrorKind::UnusedText => "unused text",
+            DecodeErrorKind::NewLengthMismatch => "new length does not match",
+        })
     }
 }
 
@@ -157,7 +595,7 @@ impl Error for OTError {
     }
 }
 
-impl OperationSeq {
+impl<S: Sequence> GenericOperationSeq<S> {
     /// Creates a store for operatations which does not need to allocate  until
     /// `capacity` operations have been stored inside.
     #[inline]
@@ -181,164 +619,133 @@ impl OperationSeq {
     /// conflicts.
     pub fn compose(&self, other: &Self) -> Result<Self, OTError> {
         if self.target_len != other.base_len {
-            return Err(OTError);
+            return Err(OTError::ComposeLengthMismatch {
+                target_len: self.target_len,
+                base_len: other.base_len,
+            });
         }
 
-        let mut new_op_seq = OperationSeq::default();
-        let mut ops1 = self.ops.iter().cloned();
-        let mut ops2 = other.ops.iter().cloned();
+        let mut new_op_seq = GenericOperationSeq::default();
+        walk::compose(
+            self.ops.iter().map(Piece::from),
+            other.ops.iter().map(Piece::from),
+            &mut new_op_seq,
+        )?;
+        Ok(new_op_seq)
+    }
 
-        let mut maybe_op1 = ops1.next();
-        let mut maybe_op2 = ops2.next();
-        loop {
-            match (&maybe_op1, &maybe_op2) {
-                (None, None) => break,
-                (Some(Operation::Delete(i)), _) => {
-                    new_op_seq.delete(*i);
-                    maybe_op1 = ops1.next();
-                }
-                (_, Some(Operation::Insert(s))) => {
-                    new_op_seq.insert(s);
-                    maybe_op2 = ops2.next();
-                }
-                (None, _) | (_, None) => {
-                    return Err(OTError);
-                }
-                (Some(Operation::Retain(i)), Some(Operation::Retain(j))) => match i.cmp(&j) {
-                    Ordering::Less => {
-                        new_op_seq.retain(*i);
-                        maybe_op2 = Some(Operation::Retain(*j - *i));
-                        maybe_op1 = ops1.next();
-                    }
-                    std::cmp::Ordering::Equal => {
-                        new_op_seq.retain(*i);
-                        maybe_op1 = ops1.next();
-                        maybe_op2 = ops2.next();
-                    }
-                    std::cmp::Ordering::Greater => {
-                        new_op_seq.retain(*j);
-                        maybe_op1 = Some(Operation::Retain(*i - *j));
-                        maybe_op2 = ops2.next();
-                    }
-                },
-                (Some(Operation::Insert(s)), Some(Operation::Delete(j))) => {
-                    match (num_chars(s.as_bytes()) as u64).cmp(j) {
-                        Ordering::Less => {
-                            maybe_op2 =
-                                Some(Operation::Delete(*j - num_chars(s.as_bytes()) as u64));
-                            maybe_op1 = ops1.next();
-                        }
-                        Ordering::Equal => {
-                            maybe_op1 = ops1.next();
-                            maybe_op2 = ops2.next();
-                        }
-                        Ordering::Greater => {
-                            maybe_op1 =
-                                Some(Operation::Insert(s.chars().skip(*j as usize).collect()));
-                            maybe_op2 = ops2.next();
-                        }
-                    }
-                }
-                (Some(Operation::Insert(s)), Some(Operation::Retain(j))) => {
-                    match (num_chars(s.as_bytes()) as u64).cmp(j) {
-                        Ordering::Less => {
-                            new_op_seq.insert(s);
-                            maybe_op2 =
-                                Some(Operation::Retain(*j - num_chars(s.as_bytes()) as u64));
-                            maybe_op1 = ops1.next();
-                        }
-                        Ordering::Equal => {
-                            new_op_seq.insert(s);
-                            maybe_op1 = ops1.next();
-                            maybe_op2 = ops2.next();
-                        }
-                        Ordering::Greater => {
-                            let chars = &mut s.chars();
-                            new_op_seq.insert(&chars.take(*j as usize).collect::<String>());
-                            maybe_op1 = Some(Operation::Insert(chars.collect()));
-                            maybe_op2 = ops2.next();
-                        }
-                    }
-                }
-                (Some(Operation::Retain(i)), Some(Operation::Delete(j))) => match i.cmp(&j) {
-                    Ordering::Less => {
-                        new_op_seq.delete(*i);
-                        maybe_op2 = Some(Operation::Delete(*j - *i));
-                        maybe_op1 = ops1.next();
-                    }
-                    Ordering::Equal => {
-                        new_op_seq.delete(*j);
-                        maybe_op2 = ops2.next();
-                        maybe_op1 = ops1.next();
-                    }
-                    Ordering::Greater => {
-                        new_op_seq.delete(*j);
-                        maybe_op1 = Some(Operation::Retain(*i - *j));
-                        maybe_op2 = ops2.next();
-                    }
-                },
-            };
+    /// Composes a list of consecutive operations into one operation. The
+    /// operations are composed pairwise in a balanced tree, so that every
+    /// operation is only copied a logarithmic number of times. Returns `None`
+    /// if there are no operations.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations are not composable due to length
+    /// conflicts.
+    pub fn compose_all<I: IntoIterator<Item = Self>>(
+        operations: I,
+    ) -> Result<Option<Self>, OTError> {
+        let mut operations: Vec<Self> = operations.into_iter().collect();
+        while operations.len() > 1 {
+            let mut composed = Vec::with_capacity(operations.len().div_ceil(2));
+            let mut operations_iter = operations.into_iter();
+            while let Some(a) = operations_iter.next() {
+                composed.push(match operations_iter.next() {
+                    Some(b) => a.compose(&b)?,
+                    None => a,
+                });
+            }
+            operations = composed;
         }
-        Ok(new_op_seq)
+        Ok(operations.pop())
+    }
+
+    /// Composes a list of consecutive operations into one operation just like
+    /// [`compose_all`](Self::compose_all), but in parallel on the rayon
+    /// thread pool. Available with the `rayon` feature.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations are not composable due to length
+    /// conflicts.
+    #[cfg(feature = "rayon")]
+    pub fn par_compose_all(operations: Vec<Self>) -> Result<Option<Self>, OTError>
+    where
+        Self: Send,
+    {
+        use rayon::prelude::*;
+
+        operations
+            .into_par_iter()
+            .map(Ok)
+            .try_reduce_with(|a, b| a.compose(&b))
+            .transpose()
     }
 
-    fn add(&mut self, op: Operation) {
+    fn add(&mut self, op: GenericOperation<S>) {
         match op {
-            Operation::Delete(i) => self.delete(i),
-            Operation::Insert(s) => self.insert(&s),
-            Operation::Retain(i) => self.retain(i),
+            GenericOperation::Delete(i) => self.delete(i),
+            GenericOperation::Insert(s) => self.push_insert(s.as_slice(), s.unit_len()),
+            GenericOperation::Retain(i) => self.retain(i),
         }
     }
 
-    /// Deletes `n` characters at the current cursor position.
+    /// Deletes `n` items at the current cursor position.
     pub fn delete(&mut self, n: u64) {
         if n == 0 {
             return;
         }
         self.base_len += n as usize;
-        if let Some(Operation::Delete(n_last)) = self.ops.last_mut() {
+        if let Some(GenericOperation::Delete(n_last)) = self.ops.last_mut() {
             *n_last += n;
         } else {
-            self.ops.push(Operation::Delete(n));
+            self.ops.push(GenericOperation::Delete(n));
         }
     }
 
     /// Inserts a `s` at the current cursor position.
-    pub fn insert(&mut self, s: &str) {
-        if s.is_empty() {
+    #[inline]
+    pub fn insert(&mut self, s: &S::Slice) {
+        self.push_insert(s, S::len(s));
+    }
+
+    // Inserts `s` whose length is already known to be `len`.
+    fn push_insert(&mut self, s: &S::Slice, len: usize) {
+        if len == 0 {
             return;
         }
-        self.target_len += num_chars(s.as_bytes());
+        self.target_len += len;
         let new_last = match self.ops.as_mut_slice() {
-            [.., Operation::Insert(s_last)] => {
-                *s_last += &s;
+            [.., GenericOperation::Insert(s_last)] => {
+                s_last.push(s, len);
                 return;
             }
-            [.., Operation::Insert(s_pre_last), Operation::Delete(_)] => {
-                *s_pre_last += s;
+            [.., GenericOperation::Insert(s_pre_last), GenericOperation::Delete(_)] => {
+                s_pre_last.push(s, len);
                 return;
             }
-            [.., op_last @ Operation::Delete(_)] => {
+            [.., op_last @ GenericOperation::Delete(_)] => {
                 let new_last = op_last.clone();
-                *op_last = Operation::Insert(s.to_owned());
+                *op_last = GenericOperation::Insert(Measured::with_len(s.to_owned(), len));
                 new_last
             }
-            _ => Operation::Insert(s.to_owned()),
+            _ => GenericOperation::Insert(Measured::with_len(s.to_owned(), len)),
         };
         self.ops.push(new_last);
     }
 
-    /// Moves the cursor `n` characters forwards.
+    /// Moves the cursor `n` items forwards.
     pub fn retain(&mut self, n: u64) {
         if n == 0 {
             return;
         }
         self.base_len += n as usize;
         self.target_len += n as usize;
-        if let Some(Operation::Retain(i_last)) = self.ops.last_mut() {
+        if let Some(GenericOperation::Retain(i_last)) = self.ops.last_mut() {
             *i_last += n;
         } else {
-            self.ops.push(Operation::Retain(n));
+            self.ops.push(GenericOperation::Retain(n));
         }
     }
 
@@ -347,148 +754,180 @@ impl OperationSeq {
     ///     `apply(apply(S, A), B') = apply(apply(S, B), A')`.
     /// This function is the heart of OT.
     ///
+    /// If both operations insert at the same position, the insert of `self`
+    /// ends up first.
+    ///
     /// # Error
     ///
     /// Returns an `OTError` if the operations cannot be transformed due to
     /// length conflicts.
+    #[inline]
     pub fn transform(&self, other: &Self) -> Result<(Self, Self), OTError> {
+        self.transform_with_priority(other, Side::Left)
+    }
+
+    /// Transforms two concurrent operations just like
+    /// [`transform`](Self::transform), but `side` decides on which side of
+    /// the insert of `other` an insert of `self` at the same position ends
+    /// up. Both peers have to pass opposite sides, e.g. by using
+    /// [`Side::from_ids`] with their site ids.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations cannot be transformed due to
+    /// length conflicts.
+    pub fn transform_with_priority(
+        &self,
+        other: &Self,
+        side: Side,
+    ) -> Result<(Self, Self), OTError> {
         if self.base_len != other.base_len {
-            return Err(OTError);
+            return Err(OTError::TransformLengthMismatch {
+                base_len: self.base_len,
+                other_base_len: other.base_len,
+            });
         }
 
-        let mut a_prime = OperationSeq::default();
-        let mut b_prime = OperationSeq::default();
+        let mut a_prime = GenericOperationSeq::default();
+        let mut b_prime = GenericOperationSeq::default();
+        walk::transform(
+            self.ops.iter().map(Piece::from),
+            other.ops.as_slice(),
+            side,
+            &mut a_prime,
+            &mut b_prime,
+        )?;
+        Ok((a_prime, b_prime))
+    }
 
-        let mut ops1 = self.ops.iter().cloned();
-        let mut ops2 = other.ops.iter().cloned();
+    /// Transforms the operation against `history`, a list of consecutive
+    /// operations that happened concurrently to it. Returns the operation
+    /// rebased onto the end of `history` together with the history rebased
+    /// onto the operation, such that
+    ///     `apply(S, compose(history..., A')) = apply(S, compose(A, history'...))`.
+    ///
+    /// `side` decides the order of concurrent inserts at the same position
+    /// just like in [`transform_with_priority`](Self::transform_with_priority).
+    /// The result is the same as transforming the operation against every
+    /// operation of `history` in turn, but it is built in a single pass.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations cannot be transformed due to
+    /// length conflicts.
+    pub fn transform_against(
+        &self,
+        history: &[Self],
+        side: Side,
+    ) -> Result<(Self, Vec<Self>), OTError> {
+        check_chain(self.base_len, history)?;
+        let mut a_prime = GenericOperationSeq::default();
+        let mut history_prime = vec![GenericOperationSeq::default(); history.len()];
+        walk::transform_chain(
+            self.ops.iter().map(Piece::from),
+            |j| history[j].ops.as_slice(),
+            side,
+            &mut a_prime,
+            &mut history_prime,
+        )?;
+        Ok((a_prime, history_prime))
+    }
 
-        let mut maybe_op1 = ops1.next();
-        let mut maybe_op2 = ops2.next();
-        loop {
-            match (&maybe_op1, &maybe_op2) {
-                (None, None) => break,
-                (Some(Operation::Insert(s)), _) => {
-                    a_prime.insert(s);
-                    b_prime.retain(num_chars(s.as_bytes()) as _);
-                    maybe_op1 = ops1.next();
-                }
-                (_, Some(Operation::Insert(s))) => {
-                    a_prime.retain(num_chars(s.as_bytes()) as _);
-                    b_prime.insert(s);
-                    maybe_op2 = ops2.next();
-                }
-                (None, _) => {
-                    return Err(OTError);
-                }
-                (_, None) => {
-                    return Err(OTError);
-                }
-                (Some(Operation::Retain(i)), Some(Operation::Retain(j))) => {
-                    match i.cmp(&j) {
-                        Ordering::Less => {
-                            a_prime.retain(*i);
-                            b_prime.retain(*i);
-                            maybe_op2 = Some(Operation::Retain(*j - *i));
-                            maybe_op1 = ops1.next();
-                        }
-                        Ordering::Equal => {
-                            a_prime.retain(*i);
-                            b_prime.retain(*i);
-                            maybe_op1 = ops1.next();
-                            maybe_op2 = ops2.next();
-                        }
-                        Ordering::Greater => {
-                            a_prime.retain(*j);
-                            b_prime.retain(*j);
-                            maybe_op1 = Some(Operation::Retain(*i - *j));
-                            maybe_op2 = ops2.next();
-                        }
-                    };
-                }
-                (Some(Operation::Delete(i)), Some(Operation::Delete(j))) => match i.cmp(&j) {
-                    Ordering::Less => {
-                        maybe_op2 = Some(Operation::Delete(*j - *i));
-                        maybe_op1 = ops1.next();
-                    }
-                    Ordering::Equal => {
-                        maybe_op1 = ops1.next();
-                        maybe_op2 = ops2.next();
-                    }
-                    Ordering::Greater => {
-                        maybe_op1 = Some(Operation::Delete(*i - *j));
-                        maybe_op2 = ops2.next();
-                    }
-                },
-                (Some(Operation::Delete(i)), Some(Operation::Retain(j))) => {
-                    match i.cmp(&j) {
-                        Ordering::Less => {
-                            a_prime.delete(*i);
-                            maybe_op2 = Some(Operation::Retain(*j - *i));
-                            maybe_op1 = ops1.next();
-                        }
-                        Ordering::Equal => {
-                            a_prime.delete(*i);
-                            maybe_op1 = ops1.next();
-                            maybe_op2 = ops2.next();
-                        }
-                        Ordering::Greater => {
-                            a_prime.delete(*j);
-                            maybe_op1 = Some(Operation::Delete(*i - *j));
-                            maybe_op2 = ops2.next();
-                        }
-                    };
-                }
-                (Some(Operation::Retain(i)), Some(Operation::Delete(j))) => {
-                    match i.cmp(&j) {
-                        Ordering::Less => {
-                            b_prime.delete(*i);
-                            maybe_op2 = Some(Operation::Delete(*j - *i));
-                            maybe_op1 = ops1.next();
-                        }
-                        Ordering::Equal => {
-                            b_prime.delete(*i);
-                            maybe_op1 = ops1.next();
-                            maybe_op2 = ops2.next();
-                        }
-                        Ordering::Greater => {
-                            b_prime.delete(*j);
-                            maybe_op1 = Some(Operation::Retain(*i - *j));
-                            maybe_op2 = ops2.next();
-                        }
-                    };
-                }
+    /// Transforms two lists of consecutive operations `a` and `b` that
+    /// happened concurrently. Returns `a` rebased onto the end of `b` and `b`
+    /// rebased onto the end of `a`, such that
+    ///     `apply(S, compose(b..., a'...)) = apply(S, compose(a..., b'...))`.
+    ///
+    /// `side` is the side of the operations of `a`, see
+    /// [`transform_with_priority`](Self::transform_with_priority).
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations cannot be transformed due to
+    /// length conflicts.
+    pub fn transform_seqs(
+        a: &[Self],
+        b: &[Self],
+        side: Side,
+    ) -> Result<(Vec<Self>, Vec<Self>), OTError> {
+        let base_len = match a.first() {
+            Some(first) => first.base_len,
+            None => return Ok((Vec::new(), b.to_vec())),
+        };
+        check_chain(base_len, b)?;
+        // The operations of `b` rebased onto the operations of `a` so far.
+        let mut b_ops: Vec<Vec<Piece<'_, S, ()>>> = b
+            .iter()
+            .map(|o| o.ops.iter().map(Piece::from).collect())
+            .collect();
+        let mut b_next = vec![Vec::new(); b.len()];
+        let mut a_prime = Vec::with_capacity(a.len());
+        let mut target_len = base_len;
+        for op in a {
+            if op.base_len != target_len {
+                return Err(OTError::TransformLengthMismatch {
+                    base_len: op.base_len,
+                    other_base_len: target_len,
+                });
             }
+            let mut op_prime = GenericOperationSeq::default();
+            walk::transform_chain(
+                op.ops.iter().map(Piece::from),
+                |j| b_ops[j].as_slice(),
+                side,
+                &mut op_prime,
+                &mut b_next,
+            )?;
+            a_prime.push(op_prime);
+            mem::swap(&mut b_ops, &mut b_next);
+            b_next.iter_mut().for_each(Vec::clear);
+            target_len = op.target_len;
         }
 
+        let b_prime = b_ops
+            .into_iter()
+            .map(|pieces| {
+                let mut o = GenericOperationSeq::default();
+                pieces.into_iter().for_each(|piece| piece.push_onto(&mut o));
+                o
+            })
+            .collect();
         Ok((a_prime, b_prime))
     }
 
-    /// Applies an operation to a string, returning a new string.
+    /// Applies an operation to a sequence, returning a new sequence.
     ///
     /// # Error
     ///
     /// Returns an error if the operation cannot be applied due to length
-    /// conflicts.
-    pub fn apply(&self, s: &str) -> Result<String, OTError> {
-        if num_chars(s.as_bytes()) != self.base_len {
-            return Err(OTError);
+    /// conflicts or if it splits a character of `s`.
+    pub fn apply(&self, s: &S::Slice) -> Result<S::Owned, OTError> {
+        if S::len(s) != self.base_len {
+            return Err(OTError::BaseLengthMismatch {
+                expected: self.base_len,
+                actual: S::len(s),
+            });
         }
-        let mut new_s = String::new();
-        let chars = &mut s.chars();
+        let mut new_s = S::Owned::default();
+        let mut rest = s;
+        let mut pos = 0;
         for op in &self.ops {
             match op {
-                Operation::Retain(retain) => {
-                    for c in chars.take(*retain as usize) {
-                        new_s.push(c);
-                    }
+                GenericOperation::Retain(retain) => {
+                    pos += *retain as usize;
+                    let (head, tail) = S::split_at(rest, *retain as usize)
+                        .ok_or(OTError::NotACharBoundary { offset: pos })?;
+                    S::push(&mut new_s, head);
+                    rest = tail;
                 }
-                Operation::Delete(delete) => {
-                    for _ in 0..*delete {
-                        chars.next();
-                    }
+                GenericOperation::Delete(delete) => {
+                    pos += *delete as usize;
+                    rest = S::split_at(rest, *delete as usize)
+                        .ok_or(OTError::NotACharBoundary { offset: pos })?
+                        .1;
                 }
-                Operation::Insert(insert) => {
-                    new_s += insert;
+                GenericOperation::Insert(insert) => {
+                    S::push(&mut new_s, insert.as_slice());
                 }
             }
         }
@@ -500,45 +939,81 @@ impl OperationSeq {
     /// an operation 'insert("hello "); skip(6);' then the inverse is
     /// 'delete("hello "); skip(6);'. The inverse should be used for
     /// implementing undo.
-    pub fn invert(&self, s: &str) -> Self {
-        let mut inverse = OperationSeq::default();
-        let chars = &mut s.chars();
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operation splits a character of `s`.
+    pub fn invert(&self, s: &S::Slice) -> Result<Self, OTError> {
+        let mut inverse = GenericOperationSeq::default();
+        let mut rest = s;
+        let mut pos = 0;
         for op in &self.ops {
             match op {
-                Operation::Retain(retain) => {
+                GenericOperation::Retain(retain) => {
                     inverse.retain(*retain);
-                    for _ in 0..*retain {
-                        chars.next();
-                    }
+                    pos += *retain as usize;
+                    rest = S::split_at(rest, *retain as usize)
+                        .ok_or(OTError::NotACharBoundary { offset: pos })?
+                        .1;
                 }
-                Operation::Insert(insert) => {
-                    inverse.delete(num_chars(insert.as_bytes()) as u64);
+                GenericOperation::Insert(insert) => {
+                    inverse.delete(insert.unit_len() as u64);
                 }
-                Operation::Delete(delete) => {
-                    inverse.insert(&chars.take(*delete as usize).collect::<String>());
+                GenericOperation::Delete(delete) => {
+                    pos += *delete as usize;
+                    let (head, tail) = S::split_at(rest, *delete as usize)
+                        .ok_or(OTError::NotACharBoundary { offset: pos })?;
+                    inverse.insert(head);
+                    rest = tail;
                 }
             }
         }
-        inverse
+        Ok(inverse)
+    }
+
+    /// Computes the position of the item at `index` after the operation has
+    /// been applied. If items are inserted exactly at `index` the bias decides
+    /// whether the new position is in front of or behind the inserted items.
+    /// Positions inside of deleted items move to the start of the deletion.
+    pub fn transform_index(&self, index: usize, bias: Bias) -> usize {
+        // The number of items of the base sequence that have been passed.
+        let mut pos = 0;
+        let mut new_index = index;
+        for op in &self.ops {
+            match op {
+                GenericOperation::Retain(retain) => {
+                    pos += *retain as usize;
+                }
+                GenericOperation::Insert(insert) => {
+                    if pos < index || bias == Bias::Right {
+                        new_index += insert.unit_len();
+                    }
+                }
+                GenericOperation::Delete(delete) => {
+                    new_index -= std::cmp::min(*delete as usize, index - pos);
+                    pos += *delete as usize;
+                }
+            }
+            if pos > index {
+                break;
+            }
+        }
+        new_index
     }
 
     /// Checks if this operation has no effect.
     #[inline]
     pub fn is_noop(&self) -> bool {
-        match self.ops.as_slice() {
-            [] => true,
-            [Operation::Retain(_)] => true,
-            _ => false,
-        }
+        matches!(self.ops.as_slice(), [] | [GenericOperation::Retain(_)])
     }
 
-    /// Returns the length of a string these operations can be applied to
+    /// Returns the length of a sequence these operations can be applied to
     #[inline]
     pub fn base_len(&self) -> usize {
         self.base_len
     }
 
-    /// Returns the length of the resulting string after the operations have
+    /// Returns the length of the resulting sequence after the operations have
     /// been applied.
     #[inline]
     pub fn target_len(&self) -> usize {
@@ -547,15 +1022,93 @@ impl OperationSeq {
 
     /// Returns the wrapped sequence of operations.
     #[inline]
-    pub fn ops(&self) -> &[Operation] {
+    pub fn ops(&self) -> &[GenericOperation<S>] {
         &self.ops
     }
 }
 
+// Checks that the operations of `history` are consecutive, starting at a
+// sequence of length `base_len`.
+fn check_chain<S: Sequence>(
+    mut base_len: usize,
+    history: &[GenericOperationSeq<S>],
+) -> Result<(), OTError> {
+    for o in history {
+        if o.base_len != base_len {
+            return Err(OTError::TransformLengthMismatch {
+                base_len,
+                other_base_len: o.base_len,
+            });
+        }
+        base_len = o.target_len;
+    }
+    Ok(())
+}
+
+impl<S: Sequence<Slice = str>> GenericOperationSeq<S> {
+    /// Converts the operation on `s` into the same operation with lengths
+    /// counted in the units of `T`, e.g. from characters to the UTF-16 code
+    /// units of a JavaScript client.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `s` does not have the base length of the
+    /// operation or if the operation splits a character of `s`.
+    pub fn convert<T: Sequence<Slice = str>>(
+        &self,
+        s: &str,
+    ) -> Result<GenericOperationSeq<T>, OTError> {
+        if S::len(s) != self.base_len {
+            return Err(OTError::BaseLengthMismatch {
+                expected: self.base_len,
+                actual: S::len(s),
+            });
+        }
+        let mut converted = GenericOperationSeq::with_capacity(self.ops.len());
+        let mut rest = s;
+        let mut pos = 0;
+        for op in &self.ops {
+            match op {
+                GenericOperation::Retain(retain) => {
+                    pos += *retain as usize;
+                    let (head, tail) = S::split_at(rest, *retain as usize)
+                        .ok_or(OTError::NotACharBoundary { offset: pos })?;
+                    converted.retain(T::len(head) as u64);
+                    rest = tail;
+                }
+                GenericOperation::Delete(delete) => {
+                    pos += *delete as usize;
+                    let (head, tail) = S::split_at(rest, *delete as usize)
+                        .ok_or(OTError::NotACharBoundary { offset: pos })?;
+                    converted.delete(T::len(head) as u64);
+                    rest = tail;
+                }
+                GenericOperation::Insert(insert) => converted.insert(insert.as_slice()),
+            }
+        }
+        Ok(converted)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::utilities::Rng;
+    use bytecount::num_chars;
+    use std::borrow::Borrow;
+
+    // Converts an operation on text into the equivalent operation on a list of
+    // characters.
+    fn to_list(o: &OperationSeq) -> ListOperationSeq<char> {
+        o.ops()
+            .iter()
+            .map(|op| match op {
+                Operation::Delete(n) => ListOperation::Delete(*n),
+                Operation::Retain(n) => ListOperation::Retain(*n),
+                Operation::Insert(s) => ListOperation::Insert(s.chars().collect::<Vec<_>>().into()),
+            })
+            .collect()
+    }
 
     #[test]
     fn lengths() {
@@ -605,7 +1158,7 @@ mod tests {
             let mut rng = Rng::default();
             let s = rng.gen_string(50);
             let o = rng.gen_operation_seq(&s);
-            let p = o.invert(&s);
+            let p = o.invert(&s).unwrap();
             assert_eq!(o.base_len, p.target_len);
             assert_eq!(o.target_len, p.base_len);
             assert_eq!(p.apply(&o.apply(&s).unwrap()).unwrap(), s);
@@ -651,10 +1204,10 @@ mod tests {
         assert_eq!(o.ops.last(), Some(&Operation::Retain(5)));
         o.insert("abc");
         assert_eq!(o.ops.len(), 2);
-        assert_eq!(o.ops.last(), Some(&Operation::Insert("abc".to_owned())));
+        assert_eq!(o.ops.last(), Some(&Operation::Insert("abc".into())));
         o.insert("xyz");
         assert_eq!(o.ops.len(), 2);
-        assert_eq!(o.ops.last(), Some(&Operation::Insert("abcxyz".to_owned())));
+        assert_eq!(o.ops.last(), Some(&Operation::Insert("abcxyz".into())));
         o.delete(1);
         assert_eq!(o.ops.len(), 3);
         assert_eq!(o.ops.last(), Some(&Operation::Delete(1)));
@@ -675,6 +1228,15 @@ mod tests {
         assert!(!o.is_noop());
     }
 
+    // Checks that the cached lengths of all inserts are correct.
+    fn check_insert_lens(o: &OperationSeq) {
+        for op in o.ops() {
+            if let Operation::Insert(s) = op {
+                assert_eq!(s.unit_len(), num_chars(s.as_bytes()));
+            }
+        }
+    }
+
     #[test]
     fn compose() {
         for _ in 0..1000 {
@@ -688,6 +1250,7 @@ mod tests {
             assert_eq!(b.target_len, num_chars(after_b.as_bytes()));
             let ab = a.compose(&b).unwrap();
             assert_eq!(ab.target_len, b.target_len);
+            check_insert_lens(&ab);
             let after_ab = ab.apply(&s).unwrap();
             assert_eq!(after_b, after_ab);
         }
@@ -701,6 +1264,8 @@ mod tests {
             let a = rng.gen_operation_seq(&s);
             let b = rng.gen_operation_seq(&s);
             let (a_prime, b_prime) = a.transform(&b).unwrap();
+            check_insert_lens(&a_prime);
+            check_insert_lens(&b_prime);
             let ab_prime = a.compose(&b_prime).unwrap();
             let ba_prime = b.compose(&a_prime).unwrap();
             let after_ab_prime = ab_prime.apply(&s).unwrap();
@@ -710,9 +1275,350 @@ mod tests {
         }
     }
 
+    #[test]
+    fn transform_with_priority() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let a = rng.gen_operation_seq(&s);
+            let b = rng.gen_operation_seq(&s);
+            let (a_prime, b_prime) = a.transform_with_priority(&b, Side::Right).unwrap();
+            assert_eq!(
+                b.transform_with_priority(&a, Side::Left).unwrap(),
+                (b_prime.clone(), a_prime.clone())
+            );
+            let ab_prime = a.compose(&b_prime).unwrap();
+            let ba_prime = b.compose(&a_prime).unwrap();
+            assert_eq!(ab_prime, ba_prime);
+            assert_eq!(ab_prime.apply(&s).unwrap(), ba_prime.apply(&s).unwrap());
+        }
+
+        let mut a = OperationSeq::default();
+        a.insert("a");
+        let mut b = OperationSeq::default();
+        b.insert("b");
+        for (side, expected) in &[(Side::Left, "ab"), (Side::Right, "ba")] {
+            let (_, b_prime) = a.transform_with_priority(&b, *side).unwrap();
+            assert_eq!(a.compose(&b_prime).unwrap().apply("").unwrap(), *expected);
+        }
+        assert_eq!(Side::from_ids("alice", "bob"), Side::Left);
+        assert_eq!(Side::from_ids(2, 1), Side::Right);
+    }
+
+    // Generates `n` consecutive operations starting at `s`.
+    fn gen_history(rng: &mut Rng, s: &str, n: usize) -> Vec<OperationSeq> {
+        let mut s = s.to_owned();
+        let mut history = Vec::with_capacity(n);
+        for _ in 0..n {
+            let o = rng.gen_operation_seq(&s);
+            s = o.apply(&s).unwrap();
+            history.push(o);
+        }
+        history
+    }
+
+    // Composes consecutive operations into one.
+    fn compose_history(s: &str, history: &[OperationSeq]) -> OperationSeq {
+        let mut composed = OperationSeq::default();
+        composed.retain(num_chars(s.as_bytes()) as u64);
+        for o in history {
+            composed = composed.compose(o).unwrap();
+        }
+        composed
+    }
+
+    #[test]
+    fn compose_all() {
+        let mut rng = Rng::default();
+        for n in 0..100 {
+            let s = rng.gen_string(20);
+            let history = gen_history(&mut rng, &s, n % 20);
+            let composed = OperationSeq::compose_all(history.clone()).unwrap();
+            if history.is_empty() {
+                assert_eq!(composed, None);
+                continue;
+            }
+            let composed = composed.unwrap();
+            assert_eq!(composed.apply(&s), compose_history(&s, &history).apply(&s));
+            #[cfg(feature = "rayon")]
+            assert_eq!(
+                OperationSeq::par_compose_all(history).unwrap(),
+                Some(composed)
+            );
+        }
+
+        let mut a = OperationSeq::default();
+        a.retain(1);
+        assert_eq!(
+            OperationSeq::compose_all(vec![a.clone(), a.clone(), OperationSeq::default()]),
+            Err(OTError::ComposeLengthMismatch {
+                target_len: 1,
+                base_len: 0
+            })
+        );
+    }
+
+    // Transforms `a` against every operation of `history` in turn.
+    fn transform_pairwise(
+        a: &OperationSeq,
+        history: &[OperationSeq],
+        side: Side,
+    ) -> (OperationSeq, Vec<OperationSeq>) {
+        let mut a_prime = a.clone();
+        let mut history_prime = Vec::with_capacity(history.len());
+        for concurrent in history {
+            let (rebased, concurrent_prime) =
+                a_prime.transform_with_priority(concurrent, side).unwrap();
+            a_prime = rebased;
+            history_prime.push(concurrent_prime);
+        }
+        (a_prime, history_prime)
+    }
+
+    #[test]
+    fn transform_against() {
+        let mut rng = Rng::default();
+        for n in 0..100 {
+            let s = rng.gen_string(20);
+            let a = rng.gen_operation_seq(&s);
+            let history = gen_history(&mut rng, &s, n % 10);
+            for &side in &[Side::Left, Side::Right] {
+                let (a_prime, history_prime) = a.transform_against(&history, side).unwrap();
+                assert_eq!(
+                    (a_prime.clone(), history_prime.clone()),
+                    transform_pairwise(&a, &history, side)
+                );
+                let mut a_then_history = vec![a.clone()];
+                a_then_history.extend(history_prime);
+                let mut history_then_a = history.clone();
+                history_then_a.push(a_prime);
+                assert_eq!(
+                    compose_history(&s, &a_then_history).apply(&s).unwrap(),
+                    compose_history(&s, &history_then_a).apply(&s).unwrap()
+                );
+            }
+        }
+
+        let mut a = OperationSeq::default();
+        a.retain(1);
+        assert_eq!(
+            a.transform_against(&[a.clone(), OperationSeq::default()], Side::Left),
+            Err(OTError::TransformLengthMismatch {
+                base_len: 1,
+                other_base_len: 0
+            })
+        );
+    }
+
+    #[test]
+    fn transform_seqs() {
+        let mut rng = Rng::default();
+        for n in 0..100 {
+            let s = rng.gen_string(20);
+            let a = gen_history(&mut rng, &s, n % 5);
+            let b = gen_history(&mut rng, &s, n / 20);
+            for &side in &[Side::Left, Side::Right] {
+                let (a_prime, b_prime) = OperationSeq::transform_seqs(&a, &b, side).unwrap();
+                let mut expected_a_prime = Vec::with_capacity(a.len());
+                let mut expected_b_prime = b.clone();
+                for op in &a {
+                    let (op_prime, history_prime) = transform_pairwise(op, &expected_b_prime, side);
+                    expected_a_prime.push(op_prime);
+                    expected_b_prime = history_prime;
+                }
+                assert_eq!(a_prime, expected_a_prime);
+                assert_eq!(b_prime, expected_b_prime);
+                let ab_prime = [a.clone(), b_prime].concat();
+                let ba_prime = [b.clone(), a_prime].concat();
+                assert_eq!(
+                    compose_history(&s, &ab_prime).apply(&s).unwrap(),
+                    compose_history(&s, &ba_prime).apply(&s).unwrap()
+                );
+            }
+        }
+        assert_eq!(
+            OperationSeq::transform_seqs(
+                &[OperationSeq::default()],
+                &[OperationSeq::default()],
+                Side::Left
+            ),
+            Ok((vec![OperationSeq::default()], vec![OperationSeq::default()]))
+        );
+        let mut a = OperationSeq::default();
+        a.retain(1);
+        a.insert("x");
+        assert_eq!(
+            OperationSeq::transform_seqs(&[a.clone(), a.clone()], &[], Side::Right),
+            Err(OTError::TransformLengthMismatch {
+                base_len: 1,
+                other_base_len: 2
+            })
+        );
+    }
+
+    // Checks that the operations `a` and `b` on `s` behave the same with
+    // lengths counted in the units of `T`.
+    fn check_units<T: Sequence<Slice = str>>(s: &str, a: &OperationSeq, b: &OperationSeq) {
+        let a_units: GenericOperationSeq<T> = a.convert(s).unwrap();
+        let b_units: GenericOperationSeq<T> = b.convert(s).unwrap();
+        let after_a = a.apply(s).unwrap();
+        assert_eq!(a_units.apply(s).unwrap().borrow(), after_a);
+        assert_eq!(a_units.target_len(), T::len(&after_a));
+        assert_eq!(&a_units.convert(s).unwrap(), a);
+        assert_eq!(
+            a_units.invert(s).unwrap().convert(&after_a).unwrap(),
+            a.invert(s).unwrap()
+        );
+
+        let (a_prime, b_prime) = a.transform(b).unwrap();
+        let (a_units_prime, b_units_prime) = a_units.transform(&b_units).unwrap();
+        let after_b = b.apply(s).unwrap();
+        assert_eq!(a_units_prime.convert(&after_b).unwrap(), a_prime);
+        assert_eq!(b_units_prime.convert(&after_a).unwrap(), b_prime);
+        let ab_prime = a.compose(&b_prime).unwrap();
+        let ab_units_prime = a_units.compose(&b_units_prime).unwrap();
+        assert_eq!(ab_units_prime.convert(s).unwrap(), ab_prime);
+    }
+
+    #[test]
+    fn utf16() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let a = rng.gen_operation_seq(&s);
+            let b = rng.gen_operation_seq(&s);
+            check_units::<Utf16>(&s, &a, &b);
+        }
+        assert_eq!(
+            OperationSeq::default().convert::<Utf16>("a"),
+            Err(OTError::BaseLengthMismatch {
+                expected: 0,
+                actual: 1
+            })
+        );
+
+        // Operations must not split a surrogate pair.
+        let mut o = Utf16OperationSeq::default();
+        o.retain(1);
+        o.delete(1);
+        let split = OTError::NotACharBoundary { offset: 1 };
+        assert_eq!(o.apply("😀"), Err(split.clone()));
+        assert_eq!(o.invert("😀"), Err(split.clone()));
+        assert_eq!(o.convert::<Chars>("😀"), Err(split.clone()));
+        let mut insert = Utf16OperationSeq::default();
+        insert.insert("😀");
+        assert_eq!(insert.compose(&o), Err(split));
+    }
+
+    #[test]
+    fn bytes() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let a = rng.gen_operation_seq(&s);
+            let b = rng.gen_operation_seq(&s);
+            check_units::<Bytes>(&s, &a, &b);
+        }
+
+        // Operations must not split a character.
+        let mut o = ByteOperationSeq::default();
+        o.retain(1);
+        o.delete(1);
+        let split = OTError::NotACharBoundary { offset: 1 };
+        assert_eq!(o.apply("ñ"), Err(split.clone()));
+        assert_eq!(o.invert("ñ"), Err(split.clone()));
+        assert_eq!(o.convert::<Chars>("ñ"), Err(split.clone()));
+        let mut insert = ByteOperationSeq::default();
+        insert.insert("ñ");
+        assert_eq!(insert.compose(&o), Err(split));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-segmentation")]
+    fn graphemes() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            // Random characters could merge into new grapheme clusters, so
+            // every character of a random text and its operations is replaced
+            // by a fixed cluster.
+            let clusters = |s: &str| -> String {
+                s.chars()
+                    .map(|c| ["a", "é", "e\u{301}", "👍🏽", "👨\u{200d}👩\u{200d}👧"][c as usize % 5])
+                    .collect()
+            };
+            let to_graphemes = |o: &OperationSeq| -> GraphemeOperationSeq {
+                o.ops()
+                    .iter()
+                    .map(|op| match op {
+                        Operation::Delete(n) => GraphemeOperation::Delete(*n),
+                        Operation::Retain(n) => GraphemeOperation::Retain(*n),
+                        Operation::Insert(s) => GraphemeOperation::Insert(clusters(s).into()),
+                    })
+                    .collect()
+            };
+            let s0 = rng.gen_string(20);
+            let s = clusters(&s0);
+            let a = to_graphemes(&rng.gen_operation_seq(&s0))
+                .convert(&s)
+                .unwrap();
+            let b = to_graphemes(&rng.gen_operation_seq(&s0))
+                .convert(&s)
+                .unwrap();
+            check_units::<Graphemes>(&s, &a, &b);
+        }
+
+        let mut o = GraphemeOperationSeq::default();
+        o.retain(1);
+        o.delete(1);
+        o.retain(1);
+        assert_eq!(
+            o.apply("e\u{301}👨\u{200d}👩\u{200d}👧x").unwrap(),
+            "e\u{301}x"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut a = OperationSeq::default();
+        a.retain(3);
+        let mut b = OperationSeq::default();
+        b.delete(2);
+        assert_eq!(
+            a.apply("ab"),
+            Err(OTError::BaseLengthMismatch {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            a.compose(&b),
+            Err(OTError::ComposeLengthMismatch {
+                target_len: 3,
+                base_len: 2
+            })
+        );
+        assert_eq!(
+            a.transform(&b),
+            Err(OTError::TransformLengthMismatch {
+                base_len: 3,
+                other_base_len: 2
+            })
+        );
+        b.base_len = 3;
+        assert_eq!(
+            a.transform(&b),
+            Err(OTError::OperationMismatch { index: 0 })
+        );
+        assert_eq!(
+            b.transform(&a),
+            Err(OTError::OperationMismatch { index: 1 })
+        );
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn serde() {
+        use ::serde::{de::value::BoolDeserializer, Deserialize};
         use serde_json;
 
         let mut rng = Rng::default();
@@ -723,6 +1629,11 @@ mod tests {
         o_exp.delete(1);
         o_exp.insert("abc");
         assert_eq!(o, o_exp);
+        check_insert_lens(&serde_json::from_str("[\"añ\",\"b\"]").unwrap());
+        assert!(matches!(
+            OperationSeq::deserialize(BoolDeserializer::<OTError>::new(true)),
+            Err(OTError::Deserialize(_))
+        ));
         for _ in 0..1000 {
             let s = rng.gen_string(20);
             let o = rng.gen_operation_seq(&s);
@@ -732,4 +1643,63 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_binary() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let bytes = bincode::serialize(&o).unwrap();
+            assert_eq!(o, bincode::deserialize(&bytes).unwrap());
+            let bytes = postcard::to_allocvec(&o).unwrap();
+            assert_eq!(o, postcard::from_bytes(&bytes).unwrap());
+            let o: Utf16OperationSeq = o.convert(&s).unwrap();
+            let bytes = postcard::to_allocvec(&o).unwrap();
+            assert_eq!(o, postcard::from_bytes(&bytes).unwrap());
+        }
+
+        let mut o = OperationSeq::default();
+        o.retain(1);
+        o.delete(2);
+        o.insert("añ");
+        let bytes = postcard::to_allocvec(&o).unwrap();
+        assert_eq!(bytes, vec![3, 0, 1, 2, 3, b'a', 0xc3, 0xb1, 1, 2]);
+        assert!(postcard::from_bytes::<OperationSeq>(&[1, 3, 1]).is_err());
+    }
+
+    #[test]
+    fn list() {
+        for _ in 0..1000 {
+            let mut rng = Rng::default();
+            let s = rng.gen_string(20);
+            let chars: Vec<char> = s.chars().collect();
+            let a = rng.gen_operation_seq(&s);
+            let b = rng.gen_operation_seq(&s);
+            let after_a = a.apply(&s).unwrap();
+            let c = rng.gen_operation_seq(&after_a);
+
+            let (a_list, b_list, c_list) = (to_list(&a), to_list(&b), to_list(&c));
+            assert_eq!(a_list.base_len(), a.base_len());
+            assert_eq!(a_list.target_len(), a.target_len());
+            assert_eq!(
+                a_list.apply(&chars).unwrap(),
+                after_a.chars().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                a_list.invert(&chars).unwrap(),
+                to_list(&a.invert(&s).unwrap())
+            );
+            assert_eq!(
+                a_list.compose(&c_list).unwrap(),
+                to_list(&a.compose(&c).unwrap())
+            );
+            let (a_prime, b_prime) = a.transform(&b).unwrap();
+            assert_eq!(
+                a_list.transform(&b_list).unwrap(),
+                (to_list(&a_prime), to_list(&b_prime))
+            );
+        }
+    }
 }
@@ -0,0 +1,785 @@
+//! Operations on rich text.
+//!
+//! Rich text operations work like the plain text operations of the crate
+//! root, but inserted and retained text may carry formatting attributes like
+//! `bold` or `link`. The semantics and lengths follow the
+//! [Delta](https://github.com/quilljs/delta) format used by Quill:
+//!
+//! - Attributes of an insert are the formatting of the inserted text.
+//! - Attributes of a retain are applied to the retained text. Setting an
+//!   attribute to `None` removes the format.
+//! - If two concurrent operations format the same text, the attributes of the
+//!   operation with priority win. Like concurrent inserts, this is decided by
+//!   the `Side` passed to
+//!   [`transform_with_priority`](RichOperationSeq::transform_with_priority).
+//!
+//! A rich text document is a `RichOperationSeq` consisting only of inserts.
+//!
+//! ```rust
+//! use operational_transform::rich_text::{AttributeValue, Attributes, RichOperationSeq};
+//!
+//! let mut bold = Attributes::new();
+//! bold.insert("bold".to_owned(), Some(AttributeValue::Bool(true)));
+//!
+//! let mut doc = RichOperationSeq::default();
+//! doc.insert("Hello World", Attributes::new());
+//!
+//! let mut o = RichOperationSeq::default();
+//! o.retain(6, bold.clone());
+//! o.retain(5, Attributes::new());
+//! let after_o = o.apply(&doc).unwrap();
+//!
+//! let mut expected = RichOperationSeq::default();
+//! expected.insert("Hello ", bold);
+//! expected.insert("World", Attributes::new());
+//! assert_eq!(after_o, expected);
+//! assert_eq!(o.invert(&doc).unwrap().apply(&after_o).unwrap(), doc);
+//! ```
+//!
+//! Like Quill, rich text operations count lengths in UTF-16 code units, so
+//! that a character outside of the Basic Multilingual Plane has a length of 2.
+//! With the `serde` feature, they are serialized to the JSON of Quill's
+//! deltas, e.g. `[{"retain":3},{"insert":"x"},{"delete":2}]`. Inserts of
+//! embeds like `{"insert":{"image":"a.png"}}` are rejected. Operations
+//! without attributes convert into a `Utf16OperationSeq` with `TryFrom`, which
+//! fails with `OTError::UnsupportedAttributes` otherwise, and from there into
+//! an `OperationSeq` on the document they apply to:
+//!
+//! ```rust
+//! use operational_transform::{
+//!     rich_text::{Attributes, RichOperationSeq},
+//!     sequence::Utf16,
+//!     OperationSeq, Utf16OperationSeq,
+//! };
+//! use std::convert::TryFrom;
+//!
+//! let mut o = RichOperationSeq::default();
+//! o.retain(2, Attributes::new());
+//! o.delete(1);
+//! let plain: OperationSeq = Utf16OperationSeq::try_from(o.clone())
+//!     .unwrap()
+//!     .convert("😀x")
+//!     .unwrap();
+//! assert_eq!(plain.apply("😀x").unwrap(), "😀");
+//! let back: Utf16OperationSeq = plain.convert::<Utf16>("😀x").unwrap();
+//! assert_eq!(RichOperationSeq::from(back), o);
+//! ```
+
+use crate::{
+    sequence::{Measured, Sequence, Utf16},
+    walk::{self, Format, Ops, Piece, Sink},
+    OTError, Side, Utf16Operation, Utf16OperationSeq,
+};
+use std::{collections::BTreeMap, convert::TryFrom, iter::FromIterator};
+
+/// The value of a formatting attribute, which mirrors a JSON value other than
+/// `null`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    /// A flag like `bold`.
+    Bool(bool),
+    /// A number like the level of a `header`.
+    Number(f64),
+    /// A string like a `color` or the target of a `link`.
+    String(String),
+    /// A list of values.
+    Array(Vec<AttributeValue>),
+    /// Values by name, like the options of a custom format.
+    Object(BTreeMap<String, AttributeValue>),
+}
+
+/// Formatting attributes by name. A value of `None` removes the attribute.
+pub type Attributes = BTreeMap<String, Option<AttributeValue>>;
+
+/// A single operation on rich text to be executed at the cursor's current
+/// position. Lengths are counted in UTF-16 code units.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RichOperation {
+    // Deletes n code units at the current cursor position.
+    Delete(u64),
+    // Moves the cursor n positions forward and applies the attributes to the
+    // text passed.
+    Retain(u64, Attributes),
+    // Inserts the string formatted with the attributes at the current cursor
+    // position.
+    Insert(Measured<Utf16>, Attributes),
+}
+
+impl<'a> From<&'a RichOperation> for Piece<'a, Utf16, &'a Attributes> {
+    #[inline]
+    fn from(op: &'a RichOperation) -> Self {
+        match op {
+            RichOperation::Delete(n) => Piece::Delete(*n),
+            RichOperation::Retain(n, attributes) => Piece::Retain(*n, attributes),
+            RichOperation::Insert(s, attributes) => {
+                Piece::Insert(s.as_slice(), s.unit_len(), attributes)
+            }
+        }
+    }
+}
+
+impl<'a> Ops<'a, Utf16, &'a Attributes> for &'a [RichOperation] {
+    #[inline]
+    fn get(self, index: usize) -> Option<Piece<'a, Utf16, &'a Attributes>> {
+        <[RichOperation]>::get(self, index).map(Piece::from)
+    }
+}
+
+impl Format for &Attributes {
+    type Owned = Attributes;
+
+    #[inline]
+    fn compose(self, other: Self, retain: bool) -> Attributes {
+        compose_attributes(self, other, retain)
+    }
+
+    #[inline]
+    fn transform(self, other: Self, wins: bool) -> Attributes {
+        if wins {
+            self.clone()
+        } else {
+            transform_attributes(other, self)
+        }
+    }
+
+    #[inline]
+    fn into_owned(self) -> Attributes {
+        self.clone()
+    }
+}
+
+/// A sequence of `RichOperation`s on rich text.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct RichOperationSeq {
+    // The consecutive operations to be applied to the target.
+    ops: Vec<RichOperation>,
+    // The required length of a document these operations can be applied to.
+    base_len: usize,
+    // The length of the resulting document after the operations have been
+    // applied.
+    target_len: usize,
+}
+
+impl FromIterator<RichOperation> for RichOperationSeq {
+    fn from_iter<T: IntoIterator<Item = RichOperation>>(ops: T) -> Self {
+        let mut operations = RichOperationSeq::default();
+        for op in ops {
+            operations.add(op);
+        }
+        operations
+    }
+}
+
+impl From<Utf16OperationSeq> for RichOperationSeq {
+    fn from(operations: Utf16OperationSeq) -> Self {
+        operations
+            .ops()
+            .iter()
+            .map(|op| match op {
+                Utf16Operation::Delete(n) => RichOperation::Delete(*n),
+                Utf16Operation::Retain(n) => RichOperation::Retain(*n, Attributes::new()),
+                Utf16Operation::Insert(s) => RichOperation::Insert(s.clone(), Attributes::new()),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<RichOperationSeq> for Utf16OperationSeq {
+    type Error = OTError;
+
+    /// Converts a rich text operation without any attributes into a plain
+    /// text operation. Use [`to_plain`](RichOperationSeq::to_plain) to drop
+    /// the attributes instead.
+    fn try_from(operations: RichOperationSeq) -> Result<Self, OTError> {
+        let mut o = Utf16OperationSeq::with_capacity(operations.ops.len());
+        for (index, op) in operations.ops.into_iter().enumerate() {
+            match op {
+                RichOperation::Delete(n) => o.delete(n),
+                RichOperation::Retain(n, attributes) if attributes.is_empty() => o.retain(n),
+                RichOperation::Insert(s, attributes) if attributes.is_empty() => {
+                    o.add(Utf16Operation::Insert(s))
+                }
+                _ => return Err(OTError::UnsupportedAttributes { index }),
+            }
+        }
+        Ok(o)
+    }
+}
+
+impl<'a> Sink<'a, Utf16, Attributes> for RichOperationSeq {
+    #[inline]
+    fn delete(&mut self, n: u64) {
+        RichOperationSeq::delete(self, n);
+    }
+
+    #[inline]
+    fn retain(&mut self, n: u64, attributes: Attributes) {
+        RichOperationSeq::retain(self, n, attributes);
+    }
+
+    #[inline]
+    fn insert(&mut self, s: &'a str, len: usize, attributes: Attributes) {
+        self.push_insert(s, len, attributes);
+    }
+}
+
+impl RichOperationSeq {
+    /// Creates a store for operatations which does not need to allocate until
+    /// `capacity` operations have been stored inside.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            ops: Vec::with_capacity(capacity),
+            base_len: 0,
+            target_len: 0,
+        }
+    }
+
+    /// Merges the operation with `other` into one operation while preserving
+    /// the changes of both. Attributes of `other` are merged into the
+    /// attributes of `self`.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations are not composable due to length
+    /// conflicts.
+    pub fn compose(&self, other: &Self) -> Result<Self, OTError> {
+        if self.target_len != other.base_len {
+            return Err(OTError::ComposeLengthMismatch {
+                target_len: self.target_len,
+                base_len: other.base_len,
+            });
+        }
+
+        let mut new_op_seq = RichOperationSeq::default();
+        walk::compose(
+            self.ops.iter().map(Piece::from),
+            other.ops.iter().map(Piece::from),
+            &mut new_op_seq,
+        )?;
+        Ok(new_op_seq)
+    }
+
+    pub(crate) fn add(&mut self, op: RichOperation) {
+        match op {
+            RichOperation::Delete(i) => self.delete(i),
+            RichOperation::Insert(s, attributes) => {
+                self.push_insert(s.as_slice(), s.unit_len(), attributes)
+            }
+            RichOperation::Retain(i, attributes) => self.retain(i, attributes),
+        }
+    }
+
+    /// Deletes `n` code units at the current cursor position.
+    pub fn delete(&mut self, n: u64) {
+        if n == 0 {
+            return;
+        }
+        self.base_len += n as usize;
+        if let Some(RichOperation::Delete(n_last)) = self.ops.last_mut() {
+            *n_last += n;
+        } else {
+            self.ops.push(RichOperation::Delete(n));
+        }
+    }
+
+    /// Inserts `s` formatted with `attributes` at the current cursor position.
+    #[inline]
+    pub fn insert(&mut self, s: &str, attributes: Attributes) {
+        self.push_insert(s, Utf16::len(s), attributes);
+    }
+
+    // Inserts `s` whose length is already known to be `len`.
+    fn push_insert(&mut self, s: &str, len: usize, attributes: Attributes) {
+        if len == 0 {
+            return;
+        }
+        self.target_len += len;
+        let new_last = match self.ops.as_mut_slice() {
+            [.., RichOperation::Insert(s_last, a_last)] if *a_last == attributes => {
+                s_last.push(s, len);
+                return;
+            }
+            [.., RichOperation::Insert(s_pre_last, a_pre_last), RichOperation::Delete(_)]
+                if *a_pre_last == attributes =>
+            {
+                s_pre_last.push(s, len);
+                return;
+            }
+            [.., op_last @ RichOperation::Delete(_)] => {
+                let new_last = op_last.clone();
+                *op_last = RichOperation::Insert(Measured::with_len(s.to_owned(), len), attributes);
+                new_last
+            }
+            _ => RichOperation::Insert(Measured::with_len(s.to_owned(), len), attributes),
+        };
+        self.ops.push(new_last);
+    }
+
+    /// Moves the cursor `n` code units forwards and applies `attributes` to
+    /// the text passed.
+    pub fn retain(&mut self, n: u64, attributes: Attributes) {
+        if n == 0 {
+            return;
+        }
+        self.base_len += n as usize;
+        self.target_len += n as usize;
+        match self.ops.last_mut() {
+            Some(RichOperation::Retain(i_last, a_last)) if *a_last == attributes => {
+                *i_last += n;
+            }
+            _ => self.ops.push(RichOperation::Retain(n, attributes)),
+        }
+    }
+
+    /// Transforms two operations A and B that happened concurrently and
+    /// produces two operations A' and B' (in an array) such that
+    ///     `apply(apply(S, A), B') = apply(apply(S, B), A')`.
+    /// If both operations insert at the same position, the insert of A ends
+    /// up first, and if both format the same text, the attributes of A win.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations cannot be transformed due to
+    /// length conflicts.
+    #[inline]
+    pub fn transform(&self, other: &Self) -> Result<(Self, Self), OTError> {
+        self.transform_with_priority(other, Side::Left)
+    }
+
+    /// Transforms two concurrent operations just like
+    /// [`transform`](Self::transform), but `side` decides which of both has
+    /// priority. With `Side::Left` the inserts of `self` end up in front of
+    /// concurrent inserts at the same position and its attributes win over
+    /// the ones `other` sets on the same text, with `Side::Right` the inserts
+    /// and attributes of `other` do. Both peers have to pass opposite sides.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operations cannot be transformed due to
+    /// length conflicts.
+    pub fn transform_with_priority(
+        &self,
+        other: &Self,
+        side: Side,
+    ) -> Result<(Self, Self), OTError> {
+        if self.base_len != other.base_len {
+            return Err(OTError::TransformLengthMismatch {
+                base_len: self.base_len,
+                other_base_len: other.base_len,
+            });
+        }
+
+        let mut a_prime = RichOperationSeq::default();
+        let mut b_prime = RichOperationSeq::default();
+        walk::transform(
+            self.ops.iter().map(Piece::from),
+            other.ops.as_slice(),
+            side,
+            &mut a_prime,
+            &mut b_prime,
+        )?;
+        Ok((a_prime, b_prime))
+    }
+
+    /// Applies an operation to a document, returning a new document.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if `doc` is not a document or if the operation cannot
+    /// be applied due to length conflicts.
+    pub fn apply(&self, doc: &Self) -> Result<Self, OTError> {
+        if !doc.is_document() {
+            return Err(OTError::NotADocument);
+        }
+        doc.compose(self)
+    }
+
+    /// Computes the inverse of an operation for the document `doc` it is
+    /// applied to. Inserted text is deleted again, deleted text is reinserted
+    /// with its original formatting and the formatting of retained text is
+    /// restored.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operation splits a surrogate pair of
+    /// `doc`.
+    pub fn invert(&self, doc: &Self) -> Result<Self, OTError> {
+        let mut inverse = RichOperationSeq::default();
+        let mut base = doc.ops.iter().filter_map(|op| match op {
+            RichOperation::Insert(s, attributes) => Some((s.as_slice(), attributes)),
+            _ => None,
+        });
+        let mut maybe_base = base.next();
+        // The number of code units of the document that have been passed.
+        let mut pos = 0;
+        // Calls `f` for every part of the document which is covered by the
+        // next `n` code units.
+        let mut walk = |mut n: u64, f: &mut dyn FnMut(&str, usize, &Attributes)| {
+            while n > 0 {
+                let (s, attributes) = match &mut maybe_base {
+                    Some((s, attributes)) if !s.is_empty() => (s, *attributes),
+                    Some(_) => {
+                        maybe_base = base.next();
+                        continue;
+                    }
+                    None => break,
+                };
+                let (part, rest) =
+                    Utf16::split_at(s, n as usize).ok_or(OTError::NotACharBoundary {
+                        offset: pos + n as usize,
+                    })?;
+                let len = Utf16::len(part);
+                n -= len as u64;
+                pos += len;
+                *s = rest;
+                f(part, len, attributes);
+            }
+            Ok(())
+        };
+        for op in &self.ops {
+            match op {
+                RichOperation::Retain(retain, attributes) if attributes.is_empty() => {
+                    inverse.retain(*retain, Attributes::new());
+                    walk(*retain, &mut |_, _, _| {})?;
+                }
+                RichOperation::Retain(retain, attributes) => {
+                    walk(*retain, &mut |_, len, base_attributes| {
+                        inverse.retain(len as u64, invert_attributes(attributes, base_attributes));
+                    })?;
+                }
+                RichOperation::Insert(insert, _) => {
+                    inverse.delete(insert.unit_len() as u64);
+                }
+                RichOperation::Delete(delete) => {
+                    walk(*delete, &mut |part, len, base_attributes| {
+                        inverse.push_insert(part, len, base_attributes.clone());
+                    })?;
+                }
+            }
+        }
+        Ok(inverse)
+    }
+
+    /// Returns the plain text operation by dropping all attributes.
+    pub fn to_plain(&self) -> Utf16OperationSeq {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                RichOperation::Delete(n) => Utf16Operation::Delete(*n),
+                RichOperation::Retain(n, _) => Utf16Operation::Retain(*n),
+                RichOperation::Insert(s, _) => Utf16Operation::Insert(s.clone()),
+            })
+            .collect()
+    }
+
+    /// Checks if this operation is a document, i.e. consists only of inserts.
+    #[inline]
+    pub fn is_document(&self) -> bool {
+        self.base_len == 0
+    }
+
+    /// Checks if this operation has no effect.
+    #[inline]
+    pub fn is_noop(&self) -> bool {
+        self.ops.iter().all(|op| match op {
+            RichOperation::Retain(_, attributes) => attributes.is_empty(),
+            _ => false,
+        })
+    }
+
+    /// Returns the length of a document these operations can be applied to
+    #[inline]
+    pub fn base_len(&self) -> usize {
+        self.base_len
+    }
+
+    /// Returns the length of the resulting document after the operations have
+    /// been applied.
+    #[inline]
+    pub fn target_len(&self) -> usize {
+        self.target_len
+    }
+
+    /// Returns the wrapped sequence of operations.
+    #[inline]
+    pub fn ops(&self) -> &[RichOperation] {
+        &self.ops
+    }
+}
+
+// Merges the attributes `b` into `a`. Removed attributes are only kept if
+// `keep_null` is set, i.e. if the result is applied to a retain.
+fn compose_attributes(a: &Attributes, b: &Attributes, keep_null: bool) -> Attributes {
+    let mut attributes = a.clone();
+    attributes.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+    if !keep_null {
+        attributes.retain(|_, v| v.is_some());
+    }
+    attributes
+}
+
+// Transforms the attributes `b` against the concurrent attributes `a` which
+// take precedence.
+fn transform_attributes(a: &Attributes, b: &Attributes) -> Attributes {
+    b.iter()
+        .filter(|(k, _)| !a.contains_key(*k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+// Computes the attributes which restore `base` after `attributes` have been
+// applied to it.
+fn invert_attributes(attributes: &Attributes, base: &Attributes) -> Attributes {
+    let mut inverted = Attributes::new();
+    for (k, v) in attributes {
+        match base.get(k) {
+            Some(base_v) if base_v != v => {
+                inverted.insert(k.clone(), base_v.clone());
+            }
+            Some(_) => {}
+            None => {
+                inverted.insert(k.clone(), None);
+            }
+        }
+    }
+    inverted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+    use rand::{prelude::*, Rng as _};
+
+    fn gen_attributes(rng: &mut StdRng, allow_null: bool) -> Attributes {
+        let values = [
+            Some(AttributeValue::Bool(true)),
+            Some(AttributeValue::Number(1.0)),
+            Some(AttributeValue::String("red".to_owned())),
+            Some(AttributeValue::Array(vec![AttributeValue::Number(2.0)])),
+            None,
+        ];
+        let values = if allow_null {
+            &values[..]
+        } else {
+            &values[..4]
+        };
+        let mut attributes = Attributes::new();
+        for k in &["bold", "header", "color", "list"] {
+            if rng.gen_bool(0.3) {
+                attributes.insert(k.to_string(), values.choose(rng).unwrap().clone());
+            }
+        }
+        attributes
+    }
+
+    fn gen_doc(rng: &mut StdRng, strings: &mut Rng) -> RichOperationSeq {
+        let mut doc = RichOperationSeq::default();
+        for _ in 0..rng.gen_range(0, 5) {
+            let s = strings.gen_string(rng.gen_range(1, 10));
+            doc.insert(&s, gen_attributes(rng, false));
+        }
+        doc
+    }
+
+    fn gen_operation_seq(
+        rng: &mut StdRng,
+        strings: &mut Rng,
+        doc: &RichOperationSeq,
+    ) -> RichOperationSeq {
+        let text: String = doc
+            .ops
+            .iter()
+            .map(|op| match op {
+                RichOperation::Insert(s, _) => s.as_slice(),
+                _ => "",
+            })
+            .collect();
+        // Retains and deletes cover whole characters of the document.
+        let mut chars = text.chars();
+        let mut o = RichOperationSeq::default();
+        while o.base_len < doc.target_len {
+            let left = chars.clone().count();
+            let n = rng.gen_range(1, left + 1);
+            let len = chars.clone().take(n).map(char::len_utf16).sum::<usize>() as u64;
+            match rng.gen_range(0, 3) {
+                0 => {
+                    o.insert(&strings.gen_string(n), gen_attributes(rng, false));
+                    continue;
+                }
+                1 => o.delete(len),
+                _ => o.retain(len, gen_attributes(rng, true)),
+            }
+            chars.nth(n - 1);
+        }
+        if rng.gen_bool(0.3) {
+            o.insert(&strings.gen_string(3), gen_attributes(rng, false));
+        }
+        o
+    }
+
+    #[test]
+    fn attributes() {
+        let mut a = Attributes::new();
+        a.insert("bold".to_owned(), Some(AttributeValue::Bool(true)));
+        a.insert(
+            "color".to_owned(),
+            Some(AttributeValue::String("red".to_owned())),
+        );
+        let mut b = Attributes::new();
+        b.insert("bold".to_owned(), None);
+        b.insert("header".to_owned(), Some(AttributeValue::Number(1.0)));
+
+        let composed = compose_attributes(&a, &b, false);
+        assert_eq!(composed.len(), 2);
+        assert_eq!(composed["header"], Some(AttributeValue::Number(1.0)));
+        assert_eq!(compose_attributes(&a, &b, true)["bold"], None);
+
+        let transformed = transform_attributes(&a, &b);
+        assert_eq!(transformed.len(), 1);
+        assert!(transformed.contains_key("header"));
+
+        let inverted = invert_attributes(&b, &a);
+        assert_eq!(inverted["bold"], Some(AttributeValue::Bool(true)));
+        assert_eq!(inverted["header"], None);
+        assert!(!inverted.contains_key("color"));
+    }
+
+    #[test]
+    fn ops_merging() {
+        let mut bold = Attributes::new();
+        bold.insert("bold".to_owned(), Some(AttributeValue::Bool(true)));
+        let mut o = RichOperationSeq::default();
+        o.retain(2, Attributes::new());
+        o.retain(2, bold.clone());
+        o.retain(2, bold.clone());
+        assert_eq!(o.ops.len(), 2);
+        o.insert("abc", bold.clone());
+        o.delete(1);
+        o.insert("def", bold.clone());
+        o.insert("ghi", Attributes::new());
+        assert_eq!(o.ops.len(), 5);
+        assert_eq!(o.base_len, 7);
+        assert_eq!(o.target_len, 15);
+        assert!(!o.is_noop());
+        assert!(RichOperationSeq::from(Utf16OperationSeq::default()).is_noop());
+    }
+
+    #[test]
+    fn plain() {
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let s = rng.gen_string(20);
+            let o: Utf16OperationSeq = rng.gen_operation_seq(&s).convert(&s).unwrap();
+            assert_eq!(RichOperationSeq::from(o.clone()).to_plain(), o);
+        }
+    }
+
+    #[test]
+    fn compose() {
+        let mut rng = StdRng::from_entropy();
+        let mut strings = Rng::default();
+        for _ in 0..1000 {
+            let doc = gen_doc(&mut rng, &mut strings);
+            let a = gen_operation_seq(&mut rng, &mut strings, &doc);
+            let after_a = a.apply(&doc).unwrap();
+            let b = gen_operation_seq(&mut rng, &mut strings, &after_a);
+            let after_b = b.apply(&after_a).unwrap();
+            let ab = a.compose(&b).unwrap();
+            assert_eq!(ab.apply(&doc).unwrap(), after_b);
+        }
+    }
+
+    #[test]
+    fn transform() {
+        let mut rng = StdRng::from_entropy();
+        let mut strings = Rng::default();
+        for _ in 0..1000 {
+            let doc = gen_doc(&mut rng, &mut strings);
+            let a = gen_operation_seq(&mut rng, &mut strings, &doc);
+            let b = gen_operation_seq(&mut rng, &mut strings, &doc);
+            for &(side, other_side) in &[(Side::Left, Side::Right), (Side::Right, Side::Left)] {
+                let (a_prime, b_prime) = a.transform_with_priority(&b, side).unwrap();
+                let after_ab_prime = b_prime.apply(&a.apply(&doc).unwrap()).unwrap();
+                let after_ba_prime = a_prime.apply(&b.apply(&doc).unwrap()).unwrap();
+                assert_eq!(after_ab_prime, after_ba_prime);
+                assert_eq!(
+                    b.transform_with_priority(&a, other_side).unwrap(),
+                    (b_prime, a_prime)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn transform_with_priority() {
+        let color = |value: &str| {
+            let mut attributes = Attributes::new();
+            attributes.insert(
+                "color".to_owned(),
+                Some(AttributeValue::String(value.to_owned())),
+            );
+            attributes
+        };
+        let mut doc = RichOperationSeq::default();
+        doc.insert("ab", Attributes::new());
+        let mut a = RichOperationSeq::default();
+        a.insert("x", Attributes::new());
+        a.retain(2, color("red"));
+        let mut b = RichOperationSeq::default();
+        b.insert("y", Attributes::new());
+        b.retain(2, color("blue"));
+
+        let mut left = RichOperationSeq::default();
+        left.insert("xy", Attributes::new());
+        left.insert("ab", color("red"));
+        let mut right = RichOperationSeq::default();
+        right.insert("yx", Attributes::new());
+        right.insert("ab", color("blue"));
+        for &(side, expected) in &[(Side::Left, &left), (Side::Right, &right)] {
+            let (a_prime, b_prime) = a.transform_with_priority(&b, side).unwrap();
+            let after_b = b.apply(&doc).unwrap();
+            assert_eq!(a_prime.apply(&after_b).unwrap(), *expected);
+            let after_a = a.apply(&doc).unwrap();
+            assert_eq!(b_prime.apply(&after_a).unwrap(), *expected);
+        }
+        assert_eq!(a.transform(&b), a.transform_with_priority(&b, Side::Left));
+    }
+
+    #[test]
+    fn invert() {
+        let mut rng = StdRng::from_entropy();
+        let mut strings = Rng::default();
+        for _ in 0..1000 {
+            let doc = gen_doc(&mut rng, &mut strings);
+            let o = gen_operation_seq(&mut rng, &mut strings, &doc);
+            let p = o.invert(&doc).unwrap();
+            assert_eq!(o.base_len, p.target_len);
+            assert_eq!(o.target_len, p.base_len);
+            assert_eq!(p.apply(&o.apply(&doc).unwrap()).unwrap(), doc);
+        }
+    }
+
+    #[test]
+    fn utf16() {
+        let mut doc = RichOperationSeq::default();
+        doc.insert("😀x", Attributes::new());
+        assert_eq!(doc.target_len(), 3);
+
+        let mut o = RichOperationSeq::default();
+        o.retain(2, Attributes::new());
+        o.delete(1);
+        let mut expected = RichOperationSeq::default();
+        expected.insert("😀", Attributes::new());
+        assert_eq!(o.apply(&doc).unwrap(), expected);
+        assert_eq!(o.invert(&doc).unwrap().apply(&expected).unwrap(), doc);
+
+        let mut o = RichOperationSeq::default();
+        o.retain(1, Attributes::new());
+        o.delete(2);
+        let err = OTError::NotACharBoundary { offset: 1 };
+        assert_eq!(o.apply(&doc), Err(err.clone()));
+        assert_eq!(o.invert(&doc), Err(err));
+    }
+}
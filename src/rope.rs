@@ -0,0 +1,148 @@
+//! Operations on ropes for large documents.
+//!
+//! Applying an operation to a `String` copies the whole document. A
+//! [`Rope`](ropey::Rope) is edited in place instead, which only costs
+//! logarithmic time per retain, delete and insert. This module is available
+//! with the `ropey` feature.
+//!
+//! ```rust
+//! use operational_transform::OperationSeq;
+//! use ropey::Rope;
+//!
+//! let mut rope = Rope::from_str("lorem ipsum");
+//! let mut o = OperationSeq::default();
+//! o.retain(6);
+//! o.delete(5);
+//! o.insert("dolor");
+//! let inverse = o.invert_rope(&rope).unwrap();
+//! o.apply_to_rope(&mut rope).unwrap();
+//! assert_eq!(rope, "lorem dolor");
+//! inverse.apply_to_rope(&mut rope).unwrap();
+//! assert_eq!(rope, "lorem ipsum");
+//! ```
+
+use crate::{buffer::TextBuffer, OTError, Operation, OperationSeq};
+use ropey::Rope;
+use std::ops::Range;
+
+impl TextBuffer for Rope {
+    #[inline]
+    fn len_chars(&self) -> usize {
+        Rope::len_chars(self)
+    }
+
+    #[inline]
+    fn insert(&mut self, at: usize, s: &str) {
+        Rope::insert(self, at, s);
+    }
+
+    #[inline]
+    fn delete(&mut self, range: Range<usize>) {
+        self.remove(range);
+    }
+}
+
+impl OperationSeq {
+    /// Applies the operation to `rope` in place.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `rope` does not have the base length of the
+    /// operation. The rope is left unchanged in that case.
+    #[inline]
+    pub fn apply_to_rope(&self, rope: &mut Rope) -> Result<(), OTError> {
+        self.apply_in_place(rope)
+    }
+
+    /// Computes the inverse of the operation for the document `rope` it is
+    /// applied to, just like [`invert`](Self::invert) does for strings.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `rope` does not have the base length of the
+    /// operation.
+    pub fn invert_rope(&self, rope: &Rope) -> Result<Self, OTError> {
+        if rope.len_chars() != self.base_len {
+            return Err(OTError::BaseLengthMismatch {
+                expected: self.base_len,
+                actual: rope.len_chars(),
+            });
+        }
+        let mut inverse = OperationSeq::default();
+        let mut pos = 0;
+        for op in &self.ops {
+            match op {
+                Operation::Retain(retain) => {
+                    inverse.retain(*retain);
+                    pos += *retain as usize;
+                }
+                Operation::Insert(insert) => {
+                    inverse.delete(insert.unit_len() as u64);
+                }
+                Operation::Delete(delete) => {
+                    let end = pos + *delete as usize;
+                    for chunk in rope.slice(pos..end).chunks() {
+                        inverse.insert(chunk);
+                    }
+                    pos = end;
+                }
+            }
+        }
+        Ok(inverse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+
+    #[test]
+    fn apply() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(50);
+            let o = rng.gen_operation_seq(&s);
+            let mut rope = Rope::from_str(&s);
+            o.apply_to_rope(&mut rope).unwrap();
+            assert_eq!(rope, o.apply(&s).unwrap().as_str());
+        }
+    }
+
+    #[test]
+    fn invert() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(50);
+            let o = rng.gen_operation_seq(&s);
+            let rope = Rope::from_str(&s);
+            assert_eq!(o.invert_rope(&rope).unwrap(), o.invert(&s).unwrap());
+        }
+    }
+
+    #[test]
+    fn base_len() {
+        let mut o = OperationSeq::default();
+        o.retain(2);
+        let mut rope = Rope::from_str("abc");
+        assert_eq!(
+            o.apply_to_rope(&mut rope),
+            Err(OTError::BaseLengthMismatch {
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(rope, "abc");
+
+        let mut o = OperationSeq::default();
+        o.retain(5);
+        o.delete(1);
+        assert_eq!(
+            o.invert_rope(&rope),
+            Err(OTError::BaseLengthMismatch {
+                expected: 6,
+                actual: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Cursors and selections that are kept in sync with the document.
+//!
+//! Positions are counted in characters just like the lengths of an
+//! `OperationSeq`. When an operation is applied to the document, the
+//! selections of all users have to be transformed against it.
+//!
+//! ```rust
+//! use operational_transform::{
+//!     selection::{Bias, Range, Selection},
+//!     OperationSeq,
+//! };
+//!
+//! let mut o = OperationSeq::default();
+//! o.insert("abc");
+//! o.retain(2);
+//! o.delete(1);
+//! o.retain(3);
+//!
+//! let selection = Selection::new(vec![Range::cursor(0), Range::new(1, 5)]);
+//! assert_eq!(
+//!     selection.transform(&o, Bias::Left),
+//!     Selection::new(vec![Range::cursor(0), Range::new(4, 7)])
+//! );
+//! assert_eq!(o.transform_index(0, Bias::Right), 3);
+//! ```
+
+use crate::OperationSeq;
+
+/// Decides where a position ends up if text is inserted right at it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bias {
+    /// The position stays in front of the inserted text.
+    Left,
+    /// The position moves behind the inserted text.
+    Right,
+}
+
+/// A range of text between two positions. The range is empty if both
+/// positions are equal, in which case it is a simple cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    /// The position where the selection was started.
+    pub anchor: usize,
+    /// The position where the cursor is.
+    pub head: usize,
+}
+
+impl Range {
+    /// Creates a range from `anchor` to `head`.
+    #[inline]
+    pub fn new(anchor: usize, head: usize) -> Self {
+        Self { anchor, head }
+    }
+
+    /// Creates an empty range at `pos`.
+    #[inline]
+    pub fn cursor(pos: usize) -> Self {
+        Self::new(pos, pos)
+    }
+
+    /// Checks if the range does not contain any text.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    /// Returns the position of the range after `operation` has been applied.
+    pub fn transform(&self, operation: &OperationSeq, bias: Bias) -> Self {
+        let anchor = operation.transform_index(self.anchor, bias);
+        if self.is_empty() {
+            Self::cursor(anchor)
+        } else {
+            Self::new(anchor, operation.transform_index(self.head, bias))
+        }
+    }
+}
+
+/// A selection consisting of one or more ranges.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// The selected ranges.
+    pub ranges: Vec<Range>,
+}
+
+impl Selection {
+    /// Creates a selection of `ranges`.
+    #[inline]
+    pub fn new(ranges: Vec<Range>) -> Self {
+        Self { ranges }
+    }
+
+    /// Creates a selection with a single cursor at `pos`.
+    #[inline]
+    pub fn cursor(pos: usize) -> Self {
+        Self::new(vec![Range::cursor(pos)])
+    }
+
+    /// Checks if any of the ranges contains text.
+    pub fn something_selected(&self) -> bool {
+        self.ranges.iter().any(|range| !range.is_empty())
+    }
+
+    /// Returns the selection after `operation` has been applied.
+    pub fn transform(&self, operation: &OperationSeq, bias: Bias) -> Self {
+        self.ranges
+            .iter()
+            .map(|range| range.transform(operation, bias))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl From<Vec<Range>> for Selection {
+    fn from(ranges: Vec<Range>) -> Self {
+        Self::new(ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utilities::Rng, Operation};
+
+    #[test]
+    fn transform_index() {
+        let mut o = OperationSeq::default();
+        o.retain(2);
+        o.insert("xy");
+        o.retain(1);
+        o.delete(2);
+        o.insert("z");
+        o.retain(1);
+
+        let left = [0, 1, 2, 5, 6, 6, 7];
+        let right = [0, 1, 4, 6, 6, 6, 7];
+        for index in 0..left.len() {
+            assert_eq!(o.transform_index(index, Bias::Left), left[index]);
+            assert_eq!(o.transform_index(index, Bias::Right), right[index]);
+        }
+    }
+
+    #[test]
+    fn transform_index_retained() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let after_o: Vec<char> = o.apply(&s).unwrap().chars().collect();
+            let mut retained = Vec::new();
+            for op in o.ops() {
+                match op {
+                    Operation::Retain(n) => retained.extend((0..*n).map(|_| true)),
+                    Operation::Delete(n) => retained.extend((0..*n).map(|_| false)),
+                    Operation::Insert(_) => {}
+                }
+            }
+            for (index, c) in s.chars().enumerate() {
+                let left = o.transform_index(index, Bias::Left);
+                let right = o.transform_index(index, Bias::Right);
+                assert!(left <= right);
+                assert!(right <= o.target_len());
+                if retained[index] {
+                    assert_eq!(after_o[right], c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn selection() {
+        let mut o = OperationSeq::default();
+        o.delete(2);
+        o.retain(2);
+        o.insert("abc");
+
+        let selection = Selection::new(vec![Range::new(3, 1), Range::cursor(4)]);
+        assert!(selection.something_selected());
+        assert_eq!(
+            selection.transform(&o, Bias::Left),
+            Selection::new(vec![Range::new(1, 0), Range::cursor(2)])
+        );
+        assert_eq!(
+            selection.transform(&o, Bias::Right),
+            Selection::new(vec![Range::new(1, 0), Range::cursor(5)])
+        );
+        assert!(!Selection::cursor(1)
+            .transform(&o, Bias::Left)
+            .something_selected());
+    }
+}
@@ -0,0 +1,423 @@
+//! Sequences operations can be applied to.
+//!
+//! The operations of this crate are not tied to text. Any sequence that can
+//! be measured, split and concatenated can be edited with retain, delete and
+//! insert operations. The types in this module select the kind of sequence
+//! and the unit lengths are counted in.
+//!
+//! ```rust
+//! use operational_transform::ListOperationSeq;
+//!
+//! let todos = vec!["milk", "eggs"];
+//! let mut o = ListOperationSeq::default();
+//! o.retain(1);
+//! o.insert(&["bread"]);
+//! o.delete(1);
+//! assert_eq!(o.apply(&todos).unwrap(), vec!["milk", "bread"]);
+//! ```
+
+use bytecount::num_chars;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::{self, Debug},
+    marker::PhantomData,
+    ops::Deref,
+};
+#[cfg(feature = "unicode-segmentation")]
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A kind of sequence operations can be applied to.
+///
+/// Implementors only select the representation of the sequence and are
+/// never instantiated.
+pub trait Sequence {
+    /// The borrowed form of the sequence, e.g. `str` for text.
+    type Slice: ?Sized + ToOwned<Owned = Self::Owned>;
+    /// The owned form of the sequence, e.g. `String` for text.
+    type Owned: Borrow<Self::Slice> + Clone + Debug + Default + PartialEq;
+
+    /// Returns the length of `s`.
+    fn len(s: &Self::Slice) -> usize;
+
+    /// Checks if `s` is empty.
+    #[inline]
+    fn is_empty(s: &Self::Slice) -> bool {
+        Self::len(s) == 0
+    }
+
+    /// Divides `s` into two at `mid`. If `mid` is larger than the length of
+    /// `s` the second part is empty. Returns `None` if `mid` is inside of a
+    /// character, which a string cannot be split at.
+    fn split_at(s: &Self::Slice, mid: usize) -> Option<(&Self::Slice, &Self::Slice)>;
+
+    /// Appends `s` to `target`.
+    fn push(target: &mut Self::Owned, s: &Self::Slice);
+}
+
+/// An owned sequence together with its length, which is computed only once.
+/// Insert operations store their sequence like this, so that its length does
+/// not have to be counted again whenever operations are composed or
+/// transformed.
+pub struct Measured<S: Sequence> {
+    // The owned sequence.
+    value: S::Owned,
+    // The length of `value` in the units of `S`.
+    len: usize,
+}
+
+impl<S: Sequence> Measured<S> {
+    /// Wraps `value` and computes its length.
+    #[inline]
+    pub fn new(value: S::Owned) -> Self {
+        let len = S::len(value.borrow());
+        Self { value, len }
+    }
+
+    // Wraps `value` whose length is already known to be `len`.
+    #[inline]
+    pub(crate) fn with_len(value: S::Owned, len: usize) -> Self {
+        Self { value, len }
+    }
+
+    /// Returns the length of the sequence in the units of `S`. Unlike the
+    /// `len` of the slice reached through `Deref`, e.g. the number of bytes
+    /// of a `str`, this is the length operations count with.
+    #[inline]
+    pub fn unit_len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the sequence is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the wrapped sequence as a slice.
+    #[inline]
+    pub fn as_slice(&self) -> &S::Slice {
+        self.value.borrow()
+    }
+
+    /// Unwraps the sequence.
+    #[inline]
+    pub fn into_inner(self) -> S::Owned {
+        self.value
+    }
+
+    // Appends `s` of length `len` to the sequence.
+    #[inline]
+    pub(crate) fn push(&mut self, s: &S::Slice, len: usize) {
+        S::push(&mut self.value, s);
+        self.len += len;
+    }
+}
+
+impl<S: Sequence> Deref for Measured<S> {
+    type Target = S::Slice;
+
+    #[inline]
+    fn deref(&self) -> &S::Slice {
+        self.as_slice()
+    }
+}
+
+impl<S: Sequence> Clone for Measured<S> {
+    fn clone(&self) -> Self {
+        Self::with_len(self.value.clone(), self.len)
+    }
+}
+
+impl<S: Sequence> Debug for Measured<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<S: Sequence> PartialEq for Measured<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<S: Sequence<Owned = String>> From<String> for Measured<S> {
+    #[inline]
+    fn from(s: String) -> Self {
+        Self::new(s)
+    }
+}
+
+impl<'a, S: Sequence<Slice = str, Owned = String>> From<&'a str> for Measured<S> {
+    #[inline]
+    fn from(s: &'a str) -> Self {
+        Self::new(s.to_owned())
+    }
+}
+
+impl<T: Clone + Debug + PartialEq> From<Vec<T>> for Measured<List<T>> {
+    #[inline]
+    fn from(v: Vec<T>) -> Self {
+        Self::new(v)
+    }
+}
+
+/// Text with lengths counted in Unicode scalar values.
+#[derive(Debug)]
+pub enum Chars {}
+
+impl Sequence for Chars {
+    type Slice = str;
+    type Owned = String;
+
+    #[inline]
+    fn len(s: &str) -> usize {
+        num_chars(s.as_bytes())
+    }
+
+    #[inline]
+    fn is_empty(s: &str) -> bool {
+        s.is_empty()
+    }
+
+    #[inline]
+    fn split_at(s: &str, mid: usize) -> Option<(&str, &str)> {
+        match s.char_indices().nth(mid) {
+            Some((i, _)) => Some(s.split_at(i)),
+            None => Some((s, "")),
+        }
+    }
+
+    #[inline]
+    fn push(target: &mut String, s: &str) {
+        target.push_str(s);
+    }
+}
+
+/// Text with lengths counted in bytes of its UTF-8 encoding.
+///
+/// A string cannot hold parts of a character, so operations that split a
+/// character are rejected with `OTError::NotACharBoundary`.
+#[derive(Debug)]
+pub enum Bytes {}
+
+impl Sequence for Bytes {
+    type Slice = str;
+    type Owned = String;
+
+    #[inline]
+    fn len(s: &str) -> usize {
+        s.len()
+    }
+
+    #[inline]
+    fn split_at(s: &str, mid: usize) -> Option<(&str, &str)> {
+        let mid = std::cmp::min(mid, s.len());
+        if s.is_char_boundary(mid) {
+            Some(s.split_at(mid))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn push(target: &mut String, s: &str) {
+        target.push_str(s);
+    }
+}
+
+/// Text with lengths counted in extended grapheme clusters, i.e. in what a
+/// user perceives as a single character. A delete never splits an emoji ZWJ
+/// sequence or a letter from its combining accents. Available with the
+/// `unicode-segmentation` feature.
+///
+/// Grapheme clusters are not always preserved when texts are concatenated,
+/// e.g. inserting a combining accent right behind a letter merges both into
+/// one cluster. The lengths of an operation count the clusters of each part
+/// on its own, so such an insert makes the operation invalid for the
+/// resulting text.
+#[cfg(feature = "unicode-segmentation")]
+#[derive(Debug)]
+pub enum Graphemes {}
+
+#[cfg(feature = "unicode-segmentation")]
+impl Sequence for Graphemes {
+    type Slice = str;
+    type Owned = String;
+
+    #[inline]
+    fn len(s: &str) -> usize {
+        s.graphemes(true).count()
+    }
+
+    #[inline]
+    fn is_empty(s: &str) -> bool {
+        s.is_empty()
+    }
+
+    #[inline]
+    fn split_at(s: &str, mid: usize) -> Option<(&str, &str)> {
+        match s.grapheme_indices(true).nth(mid) {
+            Some((i, _)) => Some(s.split_at(i)),
+            None => Some((s, "")),
+        }
+    }
+
+    #[inline]
+    fn push(target: &mut String, s: &str) {
+        target.push_str(s);
+    }
+}
+
+/// Text with lengths counted in UTF-16 code units, like the strings of
+/// JavaScript.
+///
+/// A string cannot hold half a character, so operations that split a
+/// surrogate pair are rejected with `OTError::NotACharBoundary`.
+#[derive(Debug)]
+pub enum Utf16 {}
+
+impl Sequence for Utf16 {
+    type Slice = str;
+    type Owned = String;
+
+    #[inline]
+    fn len(s: &str) -> usize {
+        s.chars().map(char::len_utf16).sum()
+    }
+
+    #[inline]
+    fn is_empty(s: &str) -> bool {
+        s.is_empty()
+    }
+
+    #[inline]
+    fn split_at(s: &str, mid: usize) -> Option<(&str, &str)> {
+        let mut units = 0;
+        for (i, c) in s.char_indices() {
+            match units.cmp(&mid) {
+                Ordering::Less => units += c.len_utf16(),
+                Ordering::Equal => return Some(s.split_at(i)),
+                Ordering::Greater => return None,
+            }
+        }
+        if units <= mid {
+            Some((s, ""))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn push(target: &mut String, s: &str) {
+        target.push_str(s);
+    }
+}
+
+/// A list of `T` with lengths counted in items.
+#[derive(Debug)]
+pub struct List<T>(PhantomData<T>);
+
+impl<T: Clone + Debug + PartialEq> Sequence for List<T> {
+    type Slice = [T];
+    type Owned = Vec<T>;
+
+    #[inline]
+    fn len(s: &[T]) -> usize {
+        s.len()
+    }
+
+    #[inline]
+    fn split_at(s: &[T], mid: usize) -> Option<(&[T], &[T])> {
+        Some(s.split_at(std::cmp::min(mid, s.len())))
+    }
+
+    #[inline]
+    fn push(target: &mut Vec<T>, s: &[T]) {
+        target.extend_from_slice(s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measured() {
+        let mut s = Measured::<Chars>::from("añ");
+        assert_eq!(s.unit_len(), 2);
+        assert!(!s.is_empty());
+        s.push("b", 1);
+        assert_eq!(s.unit_len(), 3);
+        assert_eq!(&*s, "añb");
+        assert_eq!(s.len(), 4);
+        assert_eq!(s, Measured::new("añb".to_owned()));
+        assert_eq!(format!("{:?}", s), "\"añb\"");
+        assert_eq!(s.into_inner(), "añb");
+        assert_eq!(Measured::<List<u8>>::from(vec![1, 2]).unit_len(), 2);
+    }
+
+    #[test]
+    fn chars() {
+        assert_eq!(Chars::len("añb"), 3);
+        assert!(Chars::is_empty(""));
+        assert_eq!(Chars::split_at("añb", 2), Some(("añ", "b")));
+        assert_eq!(Chars::split_at("añb", 3), Some(("añb", "")));
+        assert_eq!(Chars::split_at("añb", 4), Some(("añb", "")));
+        let mut s = "a".to_owned();
+        Chars::push(&mut s, "ñ");
+        assert_eq!(s, "añ");
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(Bytes::len("añb"), 4);
+        assert!(Bytes::is_empty(""));
+        assert_eq!(Bytes::split_at("añb", 1), Some(("a", "ñb")));
+        assert_eq!(Bytes::split_at("añb", 2), None);
+        assert_eq!(Bytes::split_at("añb", 3), Some(("añ", "b")));
+        assert_eq!(Bytes::split_at("añb", 5), Some(("añb", "")));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-segmentation")]
+    fn graphemes() {
+        let s = "e\u{301}👨\u{200d}👩\u{200d}👧x";
+        assert_eq!(Graphemes::len(s), 3);
+        assert!(Graphemes::is_empty(""));
+        assert_eq!(
+            Graphemes::split_at(s, 1),
+            Some(("e\u{301}", "👨\u{200d}👩\u{200d}👧x"))
+        );
+        assert_eq!(
+            Graphemes::split_at(s, 2),
+            Some(("e\u{301}👨\u{200d}👩\u{200d}👧", "x"))
+        );
+        assert_eq!(Graphemes::split_at(s, 4), Some((s, "")));
+    }
+
+    #[test]
+    fn utf16() {
+        assert_eq!(Utf16::len("a😀b"), 4);
+        assert!(Utf16::is_empty(""));
+        assert_eq!(Utf16::split_at("a😀b", 1), Some(("a", "😀b")));
+        assert_eq!(Utf16::split_at("a😀b", 2), None);
+        assert_eq!(Utf16::split_at("a😀b", 3), Some(("a😀", "b")));
+        assert_eq!(Utf16::split_at("😀", 1), None);
+        assert_eq!(Utf16::split_at("a😀b", 5), Some(("a😀b", "")));
+    }
+
+    #[test]
+    fn list() {
+        assert_eq!(List::len(&[1, 2, 3]), 3);
+        assert!(List::<u8>::is_empty(&[]));
+        assert_eq!(List::split_at(&[1, 2, 3], 1), Some((&[1][..], &[2, 3][..])));
+        assert_eq!(
+            List::split_at(&[1, 2, 3], 4),
+            Some((&[1, 2, 3][..], &[][..]))
+        );
+        let mut v = vec![1];
+        List::push(&mut v, &[2, 3]);
+        assert_eq!(v, vec![1, 2, 3]);
+    }
+}
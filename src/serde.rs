@@ -1,103 +1,1049 @@
-use crate::{Operation, OperationSeq};
+use crate::{
+    rich_text::{AttributeValue, Attributes, RichOperation, RichOperationSeq},
+    sequence::{Measured, Sequence},
+    GenericOperation, GenericOperationSeq, LimitKind, OTError,
+};
 use serde::{
-    de::{self, Deserializer, SeqAccess, Visitor},
-    ser::{SerializeSeq, Serializer},
+    de::{
+        self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, Unexpected,
+        VariantAccess, Visitor,
+    },
+    ser::{SerializeMap, SerializeSeq, Serializer},
     Deserialize, Serialize,
 };
-use std::fmt;
+use std::{cell::Cell, collections::BTreeMap, convert::TryFrom, fmt, marker::PhantomData};
+
+// The name and variants of the tagged form used by formats that are not
+// human readable.
+const NAME: &str = "Operation";
+const VARIANTS: &[&str] = &["Retain", "Delete", "Insert"];
 
-impl Serialize for Operation {
+impl<T: Sequence<Slice = str, Owned = String>> Serialize for GenericOperation<T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        if !serializer.is_human_readable() {
+            return match self {
+                GenericOperation::Retain(i) => {
+                    serializer.serialize_newtype_variant(NAME, 0, VARIANTS[0], i)
+                }
+                GenericOperation::Delete(i) => {
+                    serializer.serialize_newtype_variant(NAME, 1, VARIANTS[1], i)
+                }
+                GenericOperation::Insert(s) => {
+                    serializer.serialize_newtype_variant(NAME, 2, VARIANTS[2], s.as_slice())
+                }
+            };
+        }
         match self {
-            Operation::Retain(i) => serializer.serialize_u64(*i),
-            Operation::Delete(i) => serializer.serialize_i64(-(*i as i64)),
-            Operation::Insert(s) => serializer.serialize_str(s),
+            GenericOperation::Retain(i) => serializer.serialize_u64(*i),
+            GenericOperation::Delete(i) => serializer.serialize_i64(-(*i as i64)),
+            GenericOperation::Insert(s) => serializer.serialize_str(s.as_slice()),
         }
     }
 }
 
-impl<'de> Deserialize<'de> for Operation {
-    fn deserialize<D>(deserializer: D) -> Result<Operation, D::Error>
+// Visits the compact form of human readable formats as well as the tagged
+// form of other formats. In a strict deserialization, `strict` holds the
+// limits and the index of the operation, and the length of an insert is
+// checked before its text is copied.
+struct OperationVisitor<'a, T> {
+    strict: Option<(&'a Strict<'a>, usize)>,
+    marker: PhantomData<T>,
+}
+
+impl<'de, 'a, T: Sequence<Slice = str, Owned = String>> Visitor<'de> for OperationVisitor<'a, T> {
+    type Value = GenericOperation<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer between -2^64 and 2^63, a string or an operation variant")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(GenericOperation::Retain(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if value < 0 {
+            Ok(GenericOperation::Delete(value.unsigned_abs()))
+        } else {
+            Ok(GenericOperation::Retain(value as u64))
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let len = T::len(value);
+        if let Some((strict, index)) = self.strict {
+            let max = strict.limits.max_insert_len;
+            if len > max {
+                return Err(strict.exceed(LimitKind::InsertLen, max, len, index));
+            }
+        }
+        Ok(GenericOperation::Insert(Measured::with_len(
+            value.to_owned(),
+            len,
+        )))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        match data.variant()? {
+            (Variant::Retain, variant) => variant.newtype_variant().map(GenericOperation::Retain),
+            (Variant::Delete, variant) => variant.newtype_variant().map(GenericOperation::Delete),
+            (Variant::Insert, variant) => variant.newtype_variant_seed(InsertSeed(self)),
+        }
+    }
+}
+
+impl<'de, 'a, T: Sequence<Slice = str, Owned = String>> DeserializeSeed<'de>
+    for OperationVisitor<'a, T>
+{
+    type Value = GenericOperation<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct OperationVisitor;
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(self)
+        } else {
+            deserializer.deserialize_enum(NAME, VARIANTS, self)
+        }
+    }
+}
+
+// The variants of the tagged form.
+enum Variant {
+    Retain,
+    Delete,
+    Insert,
+}
 
-        impl<'de> Visitor<'de> for OperationVisitor {
-            type Value = Operation;
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D>(deserializer: D) -> Result<Variant, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct VariantVisitor;
+
+        impl<'de> Visitor<'de> for VariantVisitor {
+            type Value = Variant;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("an integer between -2^64 and 2^63 or a string")
+                formatter.write_str("an operation variant")
             }
 
             fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                Ok(Operation::Retain(value as u64))
+                match value {
+                    0 => Ok(Variant::Retain),
+                    1 => Ok(Variant::Delete),
+                    2 => Ok(Variant::Insert),
+                    _ => Err(E::invalid_value(Unexpected::Unsigned(value), &self)),
+                }
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "Retain" => Ok(Variant::Retain),
+                    "Delete" => Ok(Variant::Delete),
+                    "Insert" => Ok(Variant::Insert),
+                    _ => Err(E::unknown_variant(value, VARIANTS)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(VariantVisitor)
+    }
+}
+
+// Deserializes the text of a tagged insert.
+struct InsertSeed<'a, T>(OperationVisitor<'a, T>);
+
+impl<'de, 'a, T: Sequence<Slice = str, Owned = String>> DeserializeSeed<'de> for InsertSeed<'a, T> {
+    type Value = GenericOperation<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self.0)
+    }
+}
+
+impl<'de, T: Sequence<Slice = str, Owned = String>> Deserialize<'de> for GenericOperation<T> {
+    fn deserialize<D>(deserializer: D) -> Result<GenericOperation<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        OperationVisitor {
+            strict: None,
+            marker: PhantomData,
+        }
+        .deserialize(deserializer)
+    }
+}
+
+impl<T: Sequence<Slice = str, Owned = String>> Serialize for GenericOperationSeq<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.ops.len()))?;
+        for op in self.ops.iter() {
+            seq.serialize_element(op)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: Sequence<Slice = str, Owned = String>> Deserialize<'de> for GenericOperationSeq<T> {
+    fn deserialize<D>(deserializer: D) -> Result<GenericOperationSeq<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OperationSeqVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Sequence<Slice = str, Owned = String>> Visitor<'de> for OperationSeqVisitor<T> {
+            type Value = GenericOperationSeq<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut o = GenericOperationSeq::default();
+                while let Some(op) = seq.next_element()? {
+                    o.add(op);
+                }
+                Ok(o)
+            }
+        }
+
+        deserializer.deserialize_seq(OperationSeqVisitor(PhantomData))
+    }
+}
+
+impl Serialize for AttributeValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            AttributeValue::Bool(b) => serializer.serialize_bool(*b),
+            // JavaScript does not tell integers and floats apart, so integers
+            // are written without a fractional part.
+            AttributeValue::Number(n) if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 => {
+                serializer.serialize_i64(*n as i64)
+            }
+            AttributeValue::Number(n) => serializer.serialize_f64(*n),
+            AttributeValue::String(s) => serializer.serialize_str(s),
+            AttributeValue::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            AttributeValue::Object(values) => {
+                let mut map = serializer.serialize_map(Some(values.len()))?;
+                for (k, v) in values {
+                    map.serialize_entry(k.as_str(), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AttributeValue {
+    fn deserialize<D>(deserializer: D) -> Result<AttributeValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AttributeValueVisitor;
+
+        impl<'de> Visitor<'de> for AttributeValueVisitor {
+            type Value = AttributeValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a boolean, a number, a string, an array or an object")
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(AttributeValue::Bool(value))
             }
 
             fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                Ok(Operation::Delete((-value) as u64))
+                Ok(AttributeValue::Number(value as f64))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(AttributeValue::Number(value as f64))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(AttributeValue::Number(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(AttributeValue::String(value.to_owned()))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(AttributeValue::Array(values))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut values = BTreeMap::new();
+                while let Some((OwnedString(k), v)) = map.next_entry()? {
+                    values.insert(k, v);
+                }
+                Ok(AttributeValue::Object(values))
+            }
+        }
+
+        deserializer.deserialize_any(AttributeValueVisitor)
+    }
+}
+
+// A string owned by the deserializer, as serde is used without its `alloc`
+// feature.
+pub(crate) struct OwnedString(pub(crate) String);
+
+impl<'de> Deserialize<'de> for OwnedString {
+    fn deserialize<D>(deserializer: D) -> Result<OwnedString, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OwnedStringVisitor;
+
+        impl<'de> Visitor<'de> for OwnedStringVisitor {
+            type Value = OwnedString;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(OwnedString(value.to_owned()))
+            }
+        }
+
+        deserializer.deserialize_str(OwnedStringVisitor)
+    }
+}
+
+// The text of a rich text insert. Quill also inserts embeds like images as
+// objects, which rich text operations cannot hold.
+struct InsertText(String);
+
+impl<'de> Deserialize<'de> for InsertText {
+    fn deserialize<D>(deserializer: D) -> Result<InsertText, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct InsertTextVisitor;
+
+        impl<'de> Visitor<'de> for InsertTextVisitor {
+            type Value = InsertText;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
             }
 
             fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                Ok(Operation::Insert(value.to_owned()))
+                Ok(InsertText(value.to_owned()))
+            }
+
+            fn visit_map<A>(self, _map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                Err(de::Error::custom("embeds are not supported"))
+            }
+        }
+
+        deserializer.deserialize_any(InsertTextVisitor)
+    }
+}
+
+// The attributes of a rich text operation, which may be `null` in Quill.
+struct AttributesMap(Attributes);
+
+impl<'de> Deserialize<'de> for AttributesMap {
+    fn deserialize<D>(deserializer: D) -> Result<AttributesMap, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AttributesVisitor;
+
+        impl<'de> Visitor<'de> for AttributesVisitor {
+            type Value = AttributesMap;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of attributes")
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(AttributesMap(Attributes::new()))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut attributes = Attributes::new();
+                while let Some((OwnedString(k), v)) = map.next_entry()? {
+                    attributes.insert(k, v);
+                }
+                Ok(AttributesMap(attributes))
             }
         }
 
-        deserializer.deserialize_any(OperationVisitor)
+        deserializer.deserialize_any(AttributesVisitor)
     }
 }
 
-impl Serialize for OperationSeq {
+impl Serialize for RichOperation {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(self.ops.len()))?;
-        for op in self.ops.iter() {
+        let attributes = match self {
+            RichOperation::Delete(_) => None,
+            RichOperation::Retain(_, attributes) | RichOperation::Insert(_, attributes) => {
+                Some(attributes).filter(|attributes| !attributes.is_empty())
+            }
+        };
+        let mut map = serializer.serialize_map(Some(1 + attributes.is_some() as usize))?;
+        match self {
+            RichOperation::Delete(n) => map.serialize_entry("delete", n)?,
+            RichOperation::Retain(n, _) => map.serialize_entry("retain", n)?,
+            RichOperation::Insert(s, _) => map.serialize_entry("insert", s.as_slice())?,
+        }
+        if let Some(attributes) = attributes {
+            map.serialize_key("attributes")?;
+            map.serialize_value(&AttributesRef(attributes))?;
+        }
+        map.end()
+    }
+}
+
+// Serializes attributes without the `alloc` feature of serde.
+struct AttributesRef<'a>(&'a Attributes);
+
+impl<'a> Serialize for AttributesRef<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in self.0 {
+            map.serialize_entry(k.as_str(), v)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RichOperation {
+    fn deserialize<D>(deserializer: D) -> Result<RichOperation, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const FIELDS: &[&str] = &["insert", "retain", "delete", "attributes"];
+
+        enum Field {
+            Insert,
+            Retain,
+            Delete,
+            Attributes,
+        }
+
+        impl<'de> Deserialize<'de> for Field {
+            fn deserialize<D>(deserializer: D) -> Result<Field, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct FieldVisitor;
+
+                impl<'de> Visitor<'de> for FieldVisitor {
+                    type Value = Field;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("`insert`, `retain`, `delete` or `attributes`")
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+                    where
+                        E: de::Error,
+                    {
+                        match value {
+                            "insert" => Ok(Field::Insert),
+                            "retain" => Ok(Field::Retain),
+                            "delete" => Ok(Field::Delete),
+                            "attributes" => Ok(Field::Attributes),
+                            _ => Err(E::unknown_field(value, FIELDS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(FieldVisitor)
+            }
+        }
+
+        struct RichOperationVisitor;
+
+        impl<'de> Visitor<'de> for RichOperationVisitor {
+            type Value = RichOperation;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an insert, retain or delete")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut op = None;
+                let mut attributes = None;
+                while let Some(field) = map.next_key()? {
+                    let new_op = match field {
+                        Field::Insert => RichOperation::Insert(
+                            map.next_value::<InsertText>()?.0.into(),
+                            Attributes::new(),
+                        ),
+                        Field::Retain => {
+                            RichOperation::Retain(map.next_value()?, Attributes::new())
+                        }
+                        Field::Delete => RichOperation::Delete(map.next_value()?),
+                        Field::Attributes => {
+                            if attributes.is_some() {
+                                return Err(de::Error::duplicate_field("attributes"));
+                            }
+                            attributes = Some(map.next_value::<AttributesMap>()?.0);
+                            continue;
+                        }
+                    };
+                    if op.is_some() {
+                        return Err(de::Error::custom(
+                            "more than one of `insert`, `retain` and `delete`",
+                        ));
+                    }
+                    op = Some(new_op);
+                }
+                let attributes = attributes.unwrap_or_default();
+                match op {
+                    Some(RichOperation::Insert(s, _)) => Ok(RichOperation::Insert(s, attributes)),
+                    Some(RichOperation::Retain(n, _)) => Ok(RichOperation::Retain(n, attributes)),
+                    Some(RichOperation::Delete(_)) if !attributes.is_empty() => {
+                        Err(de::Error::custom("attributes on a delete"))
+                    }
+                    Some(op) => Ok(op),
+                    None => Err(de::Error::custom("missing `insert`, `retain` or `delete`")),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(RichOperationVisitor)
+    }
+}
+
+impl Serialize for RichOperationSeq {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.ops().len()))?;
+        for op in self.ops() {
             seq.serialize_element(op)?;
         }
         seq.end()
     }
 }
 
-impl<'de> Deserialize<'de> for OperationSeq {
-    fn deserialize<D>(deserializer: D) -> Result<OperationSeq, D::Error>
+impl<'de> Deserialize<'de> for RichOperationSeq {
+    fn deserialize<D>(deserializer: D) -> Result<RichOperationSeq, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct OperationSeqVisitor;
+        struct RichOperationSeqVisitor;
 
-        impl<'de> Visitor<'de> for OperationSeqVisitor {
-            type Value = OperationSeq;
+        impl<'de> Visitor<'de> for RichOperationSeqVisitor {
+            type Value = RichOperationSeq;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a sequence")
+                formatter.write_str("a sequence or a delta with `ops`")
             }
 
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
             where
                 A: SeqAccess<'de>,
             {
-                let mut o = OperationSeq::default();
+                let mut o = RichOperationSeq::default();
                 while let Some(op) = seq.next_element()? {
                     o.add(op);
                 }
                 Ok(o)
             }
+
+            // Quill serializes a `Delta` as an object holding the operations.
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut o = None;
+                while let Some(OwnedString(key)) = map.next_key()? {
+                    if key != "ops" {
+                        return Err(de::Error::unknown_field(&key, &["ops"]));
+                    }
+                    if o.is_some() {
+                        return Err(de::Error::duplicate_field("ops"));
+                    }
+                    o = Some(map.next_value()?);
+                }
+                o.ok_or_else(|| de::Error::missing_field("ops"))
+            }
         }
 
-        deserializer.deserialize_seq(OperationSeqVisitor)
+        deserializer.deserialize_any(RichOperationSeqVisitor)
+    }
+}
+
+/// Limits for the strict deserialization of operations from untrusted input
+/// with [`deserialize_strict`](GenericOperationSeq::deserialize_strict). No
+/// limits are enforced by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum number of operations in the input.
+    pub max_ops: usize,
+    /// The maximum length of a single insert.
+    pub max_insert_len: usize,
+    /// The maximum base length of the operation.
+    pub max_base_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_ops: usize::MAX,
+            max_insert_len: usize::MAX,
+            max_base_len: usize::MAX,
+        }
+    }
+}
+
+// The limits of a strict deserialization together with the reason the input
+// was rejected for, as the error of a deserializer cannot hold it.
+struct Strict<'a> {
+    limits: &'a Limits,
+    rejected: Cell<Option<OTError>>,
+}
+
+impl Strict<'_> {
+    // Keeps `err` and returns the error for the deserializer to stop with.
+    fn reject<E: de::Error>(&self, err: OTError) -> E {
+        let de_err = E::custom(&err);
+        self.rejected.set(Some(err));
+        de_err
+    }
+
+    fn exceed<E: de::Error>(&self, kind: LimitKind, max: usize, actual: usize, index: usize) -> E {
+        self.reject(OTError::LimitExceeded {
+            kind,
+            max,
+            actual,
+            index,
+        })
+    }
+}
+
+impl<T: Sequence<Slice = str, Owned = String>> GenericOperationSeq<T> {
+    /// Deserializes an operation like its `Deserialize` implementation does,
+    /// but rejects retains, deletes and inserts of length zero as well as
+    /// input exceeding `limits`. Inserts are checked before their text is
+    /// copied.
+    ///
+    /// ```rust
+    /// use operational_transform::{serde::Limits, LimitKind, OTError, OperationSeq};
+    ///
+    /// let limits = Limits {
+    ///     max_insert_len: 3,
+    ///     ..Limits::default()
+    /// };
+    /// let mut de = serde_json::Deserializer::from_str("[1,-1,\"abc\"]");
+    /// assert!(OperationSeq::deserialize_strict(&mut de, &limits).is_ok());
+    /// let mut de = serde_json::Deserializer::from_str("[1,-1,\"abcd\"]");
+    /// assert_eq!(
+    ///     OperationSeq::deserialize_strict(&mut de, &limits),
+    ///     Err(OTError::LimitExceeded {
+    ///         kind: LimitKind::InsertLen,
+    ///         max: 3,
+    ///         actual: 4,
+    ///         index: 2
+    ///     })
+    /// );
+    /// let mut de = serde_json::Deserializer::from_str("[1,0,\"abc\"]");
+    /// assert_eq!(
+    ///     OperationSeq::deserialize_strict(&mut de, &limits),
+    ///     Err(OTError::EmptyComponent { index: 1 })
+    /// );
+    /// ```
+    ///
+    /// # Error
+    ///
+    /// Returns `OTError::EmptyComponent` for an operation of length zero,
+    /// `OTError::LimitExceeded` if the input violates the limits and
+    /// `OTError::Deserialize` with the message of the deserializer if it is
+    /// malformed.
+    pub fn deserialize_strict<'de, D>(deserializer: D, limits: &Limits) -> Result<Self, OTError>
+    where
+        D: Deserializer<'de>,
+    {
+        struct StrictVisitor<'a, T> {
+            strict: &'a Strict<'a>,
+            marker: PhantomData<T>,
+        }
+
+        impl<'de, 'a, T: Sequence<Slice = str, Owned = String>> Visitor<'de> for StrictVisitor<'a, T> {
+            type Value = GenericOperationSeq<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let strict = self.strict;
+                let limits = strict.limits;
+                let mut o = GenericOperationSeq::default();
+                let mut index = 0;
+                while let Some(op) = seq.next_element_seed(OperationVisitor::<T> {
+                    strict: Some((strict, index)),
+                    marker: PhantomData,
+                })? {
+                    if index == limits.max_ops {
+                        return Err(strict.exceed(
+                            LimitKind::Ops,
+                            limits.max_ops,
+                            index + 1,
+                            index,
+                        ));
+                    }
+                    let n = match &op {
+                        GenericOperation::Retain(n) | GenericOperation::Delete(n) => *n,
+                        GenericOperation::Insert(s) => s.unit_len() as u64,
+                    };
+                    if n == 0 {
+                        return Err(strict.reject(OTError::EmptyComponent { index }));
+                    }
+                    match op {
+                        GenericOperation::Retain(_) | GenericOperation::Delete(_)
+                            if n > (limits.max_base_len - o.base_len) as u64 =>
+                        {
+                            let actual = usize::try_from(n)
+                                .ok()
+                                .and_then(|n| n.checked_add(o.base_len))
+                                .unwrap_or(usize::MAX);
+                            return Err(strict.exceed(
+                                LimitKind::BaseLen,
+                                limits.max_base_len,
+                                actual,
+                                index,
+                            ));
+                        }
+                        _ => o.add(op),
+                    }
+                    index += 1;
+                }
+                Ok(o)
+            }
+        }
+
+        let strict = Strict {
+            limits,
+            rejected: Cell::new(None),
+        };
+        deserializer
+            .deserialize_seq(StrictVisitor {
+                strict: &strict,
+                marker: PhantomData,
+            })
+            .map_err(|err| {
+                strict
+                    .rejected
+                    .take()
+                    .unwrap_or_else(|| OTError::Deserialize(err.to_string()))
+            })
+    }
+}
+
+impl de::Error for OTError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        OTError::Deserialize(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utilities::Rng, OperationSeq, Utf16OperationSeq};
+    use std::convert::TryFrom;
+
+    fn from_str_strict(s: &str, limits: &Limits) -> Result<OperationSeq, OTError> {
+        let mut de = serde_json::Deserializer::from_str(s);
+        OperationSeq::deserialize_strict(&mut de, limits)
+    }
+
+    #[test]
+    fn strict() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let json = serde_json::to_string(&o).unwrap();
+            assert_eq!(from_str_strict(&json, &Limits::default()).unwrap(), o);
+            let bytes = postcard::to_allocvec(&o).unwrap();
+            let mut de = postcard::Deserializer::from_bytes(&bytes);
+            let o_strict = OperationSeq::deserialize_strict(&mut de, &Limits::default());
+            assert_eq!(o_strict.unwrap(), o);
+        }
+
+        let limits = Limits {
+            max_ops: 3,
+            max_insert_len: 2,
+            max_base_len: 5,
+        };
+        assert!(from_str_strict("[2,-3,\"añ\"]", &limits).is_ok());
+        assert_eq!(
+            from_str_strict("[2,0]", &limits),
+            Err(OTError::EmptyComponent { index: 1 })
+        );
+        assert_eq!(
+            from_str_strict("[\"\"]", &limits),
+            Err(OTError::EmptyComponent { index: 0 })
+        );
+        match from_str_strict("[-0]", &limits) {
+            Err(OTError::Deserialize(err)) => {
+                assert!(
+                    err.starts_with("invalid type: floating point `-0.0`"),
+                    "{:?}",
+                    err
+                )
+            }
+            res => panic!("[-0] gave {:?}", res),
+        }
+        let exceeding_inputs = [
+            ("[1,1,1,1]", LimitKind::Ops, 3, 4, 3),
+            ("[\"abc\"]", LimitKind::InsertLen, 2, 3, 0),
+            ("[3,-3]", LimitKind::BaseLen, 5, 6, 1),
+            (
+                "[18446744073709551615]",
+                LimitKind::BaseLen,
+                5,
+                usize::MAX,
+                0,
+            ),
+        ];
+        for &(input, kind, max, actual, index) in exceeding_inputs.iter() {
+            assert_eq!(
+                from_str_strict(input, &limits),
+                Err(OTError::LimitExceeded {
+                    kind,
+                    max,
+                    actual,
+                    index
+                }),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            from_str_strict("[1,\"abc\"]", &limits)
+                .unwrap_err()
+                .to_string(),
+            "insert length of 3 exceeds the maximum of 2 at operation 1"
+        );
+
+        // The tagged form is checked as well.
+        let mut o = OperationSeq::default();
+        o.insert("abc");
+        let bytes = postcard::to_allocvec(&o).unwrap();
+        let mut de = postcard::Deserializer::from_bytes(&bytes);
+        assert_eq!(
+            OperationSeq::deserialize_strict(&mut de, &limits),
+            Err(OTError::LimitExceeded {
+                kind: LimitKind::InsertLen,
+                max: 2,
+                actual: 3,
+                index: 0
+            })
+        );
+
+        // Insert lengths are measured in units of the sequence.
+        let mut de = serde_json::Deserializer::from_str("[\"😀\"]");
+        assert!(Utf16OperationSeq::deserialize_strict(&mut de, &limits).is_ok());
+        let mut de = serde_json::Deserializer::from_str("[\"😀😀\"]");
+        assert!(matches!(
+            Utf16OperationSeq::deserialize_strict(&mut de, &limits),
+            Err(OTError::LimitExceeded { actual: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn quill_delta() {
+        let fixtures = [
+            r#"[{"retain":3},{"insert":"x"},{"delete":2}]"#,
+            r#"[{"insert":"Hello "},{"insert":"World","attributes":{"bold":true}},{"insert":"\n"}]"#,
+            r#"[{"retain":5,"attributes":{"bold":null,"color":"red","header":1,"size":1.5}}]"#,
+            r#"[{"insert":"x","attributes":{"mention":{"id":7,"tags":["a",true]}}}]"#,
+        ];
+        for json in fixtures.iter() {
+            let o: RichOperationSeq = serde_json::from_str(json).unwrap();
+            assert_eq!(serde_json::to_string(&o).unwrap(), *json);
+        }
+
+        let o: RichOperationSeq = serde_json::from_str(fixtures[3]).unwrap();
+        let mut mention = BTreeMap::new();
+        mention.insert("id".to_owned(), AttributeValue::Number(7.0));
+        mention.insert(
+            "tags".to_owned(),
+            AttributeValue::Array(vec![
+                AttributeValue::String("a".to_owned()),
+                AttributeValue::Bool(true),
+            ]),
+        );
+        let mut attributes = Attributes::new();
+        attributes.insert("mention".to_owned(), Some(AttributeValue::Object(mention)));
+        let mut o_exp = RichOperationSeq::default();
+        o_exp.insert("x", attributes);
+        assert_eq!(o, o_exp);
+
+        let o: RichOperationSeq = serde_json::from_str(fixtures[0]).unwrap();
+        let mut o_exp = Utf16OperationSeq::default();
+        o_exp.retain(3);
+        o_exp.insert("x");
+        o_exp.delete(2);
+        assert_eq!(Utf16OperationSeq::try_from(o), Ok(o_exp));
+        let doc: RichOperationSeq = serde_json::from_str(fixtures[1]).unwrap();
+        assert!(doc.is_document());
+        assert_eq!(
+            Utf16OperationSeq::try_from(doc.clone()),
+            Err(OTError::UnsupportedAttributes { index: 1 })
+        );
+        // Quill counts the emoji as two code units.
+        let emoji_doc: RichOperationSeq = serde_json::from_str(r#"[{"insert":"😀x\n"}]"#).unwrap();
+        let mut o: RichOperationSeq =
+            serde_json::from_str(r#"[{"retain":2},{"delete":1}]"#).unwrap();
+        assert_eq!(emoji_doc.target_len(), 4);
+        // Quill leaves the retain of the rest of the document implicit.
+        o.retain(1, Attributes::new());
+        assert_eq!(
+            serde_json::to_string(&o.apply(&emoji_doc).unwrap()).unwrap(),
+            r#"[{"insert":"😀\n"}]"#
+        );
+
+        let json = format!(r#"{{"ops":{}}}"#, fixtures[1]);
+        assert_eq!(
+            serde_json::from_str::<RichOperationSeq>(&json).unwrap(),
+            doc
+        );
+
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o: Utf16OperationSeq = rng.gen_operation_seq(&s).convert(&s).unwrap();
+            let json = serde_json::to_string(&RichOperationSeq::from(o.clone())).unwrap();
+            let rich: RichOperationSeq = serde_json::from_str(&json).unwrap();
+            assert_eq!(Utf16OperationSeq::try_from(rich), Ok(o));
+        }
+
+        let invalid_inputs = [
+            r#"[{"delete":1,"attributes":{"bold":true}}]"#,
+            r#"[{"retain":1,"delete":1}]"#,
+            r#"[{"retain":1,"attributes":{},"attributes":{}}]"#,
+            r#"[{"retain":-1}]"#,
+            r#"[{"attributes":{"bold":true}}]"#,
+            r#"[{"bold":true}]"#,
+            r#"{"op":[]}"#,
+        ];
+        for json in invalid_inputs.iter() {
+            assert!(
+                serde_json::from_str::<RichOperationSeq>(json).is_err(),
+                "{}",
+                json
+            );
+        }
+        let err = serde_json::from_str::<RichOperationSeq>(r#"[{"insert":{"image":"a.png"}}]"#)
+            .unwrap_err();
+        assert!(err.to_string().starts_with("embeds are not supported"));
+    }
+
+    #[test]
+    fn signed_counts() {
+        use ::serde::de::{value::I64Deserializer, Deserialize};
+
+        let op = GenericOperation::<crate::Chars>::deserialize(I64Deserializer::<OTError>::new(3));
+        assert_eq!(op, Ok(GenericOperation::Retain(3)));
+        let op = GenericOperation::<crate::Chars>::deserialize(I64Deserializer::<OTError>::new(
+            i64::MIN,
+        ));
+        assert_eq!(op, Ok(GenericOperation::Delete(1 << 63)));
     }
 }
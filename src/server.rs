@@ -0,0 +1,200 @@
+//! Server side revision history.
+//!
+//! The server owns the authoritative document and the list of all operations
+//! that have been applied to it. Operations that were created against an
+//! older revision of the document are transformed against all operations
+//! committed since, before they are applied and broadcast to the other
+//! clients.
+//!
+//! ```rust
+//! use operational_transform::{server::Server, OperationSeq};
+//!
+//! let mut server = Server::new("abc".to_owned());
+//!
+//! let mut a = OperationSeq::default();
+//! a.retain(3);
+//! a.insert("def");
+//! server.receive_operation(0, a).unwrap();
+//!
+//! // `b` was created concurrently to `a` and is still based on revision 0.
+//! let mut b = OperationSeq::default();
+//! b.insert("ghi");
+//! b.retain(3);
+//! let b_prime = server.receive_operation(0, b).unwrap();
+//!
+//! assert_eq!(b_prime.base_len(), 6);
+//! assert_eq!(server.document(), "ghiabcdef");
+//! assert_eq!(server.revision(), 2);
+//! ```
+
+use crate::{OTError, OperationSeq};
+
+/// Keeps the current document together with its revision history.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Server {
+    // The document after all operations have been applied.
+    document: String,
+    // All operations that have been applied to the document in the order
+    // they were received. The index of an operation is the revision it was
+    // applied to.
+    operations: Vec<OperationSeq>,
+}
+
+impl Server {
+    /// Creates a server for `document` with an empty history.
+    pub fn new(document: String) -> Self {
+        Self {
+            document,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Returns the current document.
+    #[inline]
+    pub fn document(&self) -> &str {
+        &self.document
+    }
+
+    /// Returns the current revision which is the number of operations that
+    /// have been applied to the document.
+    #[inline]
+    pub fn revision(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns all operations that have been applied to the document.
+    #[inline]
+    pub fn operations(&self) -> &[OperationSeq] {
+        &self.operations
+    }
+
+    /// Handles an operation that a client created against `revision`. The
+    /// operation is transformed against all operations committed since and
+    /// then applied to the document. Returns the transformed operation which
+    /// should be broadcast to all other clients.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if `revision` is ahead of the server or if the
+    /// operation does not fit the document at `revision`. The server is left
+    /// unchanged in that case.
+    pub fn receive_operation(
+        &mut self,
+        revision: usize,
+        mut operation: OperationSeq,
+    ) -> Result<OperationSeq, OTError> {
+        if revision > self.operations.len() {
+            return Err(OTError::RevisionMismatch {
+                revision,
+                latest: self.operations.len(),
+            });
+        }
+        for concurrent in &self.operations[revision..] {
+            operation = operation.transform(concurrent)?.0;
+        }
+        self.document = operation.apply(&self.document)?;
+        self.operations.push(operation.clone());
+        Ok(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::Client, utilities::Rng};
+
+    #[test]
+    fn receive_operation() {
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let s = rng.gen_string(20);
+            let mut server = Server::new(s.clone());
+            let a = rng.gen_operation_seq(&s);
+            let b = rng.gen_operation_seq(&s);
+            let (b_prime, _) = b.transform(&a).unwrap();
+            let after_ab_prime = a.compose(&b_prime).unwrap().apply(&s).unwrap();
+            assert_eq!(server.receive_operation(0, a.clone()).unwrap(), a);
+            assert_eq!(server.receive_operation(0, b).unwrap(), b_prime);
+            assert_eq!(server.revision(), 2);
+            assert_eq!(server.operations(), &[a, b_prime][..]);
+            assert_eq!(server.document(), after_ab_prime);
+        }
+    }
+
+    #[test]
+    fn reject() {
+        let mut server = Server::new("abc".to_owned());
+        let mut o = OperationSeq::default();
+        o.retain(3);
+        assert_eq!(
+            server.receive_operation(1, o.clone()),
+            Err(OTError::RevisionMismatch {
+                revision: 1,
+                latest: 0
+            })
+        );
+        o.retain(1);
+        assert_eq!(
+            server.receive_operation(0, o),
+            Err(OTError::BaseLengthMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(server, Server::new("abc".to_owned()));
+    }
+
+    // Delivers the oldest operation in `outbox` to the server and the
+    // resulting acknowledgement and broadcast to the clients.
+    fn deliver(
+        server: &mut Server,
+        clients: &mut [(Client, String)],
+        outbox: &mut Vec<(usize, usize, OperationSeq)>,
+    ) {
+        let (sender, revision, o) = outbox.remove(0);
+        let o = server.receive_operation(revision, o).unwrap();
+        for (i, (client, doc)) in clients.iter_mut().enumerate() {
+            if i == sender {
+                if let Some((revision, o)) = client.server_ack().unwrap() {
+                    outbox.push((i, revision, o));
+                }
+            } else {
+                *doc = client.apply_server(&o).unwrap().apply(doc).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn clients() {
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let s = rng.gen_string(20);
+            let mut server = Server::new(s.clone());
+            let mut clients = vec![(Client::new(0), s.clone()), (Client::new(0), s)];
+            // Operations sent by the clients which have not reached the server
+            // yet, together with the index of the sender and their revision.
+            let mut outbox = Vec::new();
+
+            for step in 0..20 {
+                let i = step % clients.len();
+                let (client, doc) = &mut clients[i];
+                let o = rng.gen_operation_seq(doc);
+                *doc = o.apply(doc).unwrap();
+                if let Some((revision, o)) = client.apply_client(o).unwrap() {
+                    outbox.push((i, revision, o));
+                }
+                if step % 3 == 0 && !outbox.is_empty() {
+                    deliver(&mut server, &mut clients, &mut outbox);
+                }
+            }
+            while !outbox.is_empty() {
+                deliver(&mut server, &mut clients, &mut outbox);
+            }
+
+            for (client, doc) in &clients {
+                assert_eq!(client.revision(), server.revision());
+                assert_eq!(doc, server.document());
+            }
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! The wire formats of ShareDB's `text` and `text-unicode` types.
+//!
+//! An operation of both types is a list of components:
+//!
+//! - `n`: skips `n` items
+//! - `"s"`: inserts `s`
+//! - `{"d": n}`: deletes `n` items
+//! - `{"d": "s"}`: deletes the text `s`, which only `text-unicode` produces
+//!
+//! The types differ in how they count items. `text` counts UTF-16 code units
+//! like JavaScript strings do and maps to a
+//! [`Utf16OperationSeq`](crate::Utf16OperationSeq), while `text-unicode`
+//! counts code points and maps to an [`OperationSeq`](crate::OperationSeq).
+//! The same applies to cursors: ShareDB transforms them like
+//! [`transform_index`](crate::GenericOperationSeq::transform_index) with
+//! [`Bias::Left`](crate::selection::Bias::Left) does, but in the units of the
+//! respective type.
+//!
+//! ShareDB leaves out the skip up to the end of the document, so the length
+//! of the document is needed to decode an operation. This module is available
+//! with the `serde` feature.
+//!
+//! ```rust
+//! use operational_transform::{sharedb::Component, OperationSeq, Utf16OperationSeq};
+//!
+//! let components: Vec<Component> = serde_json::from_str(r#"[1,"😀",{"d":1}]"#).unwrap();
+//! let o = OperationSeq::from_sharedb(&components, 3).unwrap();
+//! assert_eq!(o.apply("abc").unwrap(), "a😀c");
+//! assert_eq!(o.to_sharedb(), components);
+//!
+//! let o: Utf16OperationSeq = o.convert("abc").unwrap();
+//! assert_eq!(serde_json::to_string(&o.to_sharedb()).unwrap(), r#"[1,"😀",{"d":1}]"#);
+//! assert_eq!(o.target_len(), 4);
+//! ```
+
+use crate::{sequence::Sequence, GenericOperation, GenericOperationSeq, OTError};
+use serde::{
+    de::{self, Deserializer, MapAccess, Visitor},
+    ser::{SerializeMap, Serializer},
+    Deserialize, Serialize,
+};
+use std::{convert::TryFrom, fmt};
+
+/// A component of a ShareDB text operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Component {
+    /// Skips the given number of items.
+    Skip(u64),
+    /// Inserts the text.
+    Insert(String),
+    /// Deletes the given number of items.
+    Delete(u64),
+    /// Deletes the given text, which makes the operation invertible.
+    DeleteText(String),
+}
+
+impl<S: Sequence<Slice = str, Owned = String>> GenericOperationSeq<S> {
+    /// Creates an operation from the ShareDB `components` for a document of
+    /// length `doc_len`.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the components exceed the document or if a
+    /// count overflows.
+    pub fn from_sharedb(components: &[Component], doc_len: usize) -> Result<Self, OTError> {
+        let mut o = Self::with_capacity(components.len() + 1);
+        for (index, component) in components.iter().enumerate() {
+            let n = match component {
+                Component::Skip(n) | Component::Delete(n) => *n,
+                Component::DeleteText(s) => S::len(s) as u64,
+                Component::Insert(s) => {
+                    o.insert(s);
+                    continue;
+                }
+            };
+            // The counts are untrusted, so the base length is checked before
+            // the operation grows.
+            let base_len = usize::try_from(n)
+                .ok()
+                .and_then(|n| o.base_len.checked_add(n))
+                .ok_or(OTError::CountOverflow { index })?;
+            if base_len > doc_len {
+                return Err(OTError::BaseLengthMismatch {
+                    expected: doc_len,
+                    actual: base_len,
+                });
+            }
+            match component {
+                Component::Skip(_) => o.retain(n),
+                _ => o.delete(n),
+            }
+        }
+        o.retain((doc_len - o.base_len) as u64);
+        Ok(o)
+    }
+
+    /// Converts the operation into ShareDB components, leaving out the
+    /// trailing retain.
+    pub fn to_sharedb(&self) -> Vec<Component> {
+        let ops = match self.ops.split_last() {
+            Some((GenericOperation::Retain(_), ops)) => ops,
+            _ => &self.ops,
+        };
+        ops.iter()
+            .map(|op| match op {
+                GenericOperation::Retain(n) => Component::Skip(*n),
+                GenericOperation::Insert(s) => Component::Insert(s.as_slice().to_owned()),
+                GenericOperation::Delete(n) => Component::Delete(*n),
+            })
+            .collect()
+    }
+}
+
+impl Serialize for Component {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Component::Skip(n) => serializer.serialize_u64(*n),
+            Component::Insert(s) => serializer.serialize_str(s),
+            Component::Delete(n) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("d", n)?;
+                map.end()
+            }
+            Component::DeleteText(s) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("d", s)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Component {
+    fn deserialize<D>(deserializer: D) -> Result<Component, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ComponentVisitor;
+
+        impl<'de> Visitor<'de> for ComponentVisitor {
+            type Value = Component;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a non-negative integer, a string or a delete object")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Component::Skip(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                if value < 0 {
+                    return Err(E::invalid_value(de::Unexpected::Signed(value), &self));
+                }
+                Ok(Component::Skip(value as u64))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Component::Insert(value.to_owned()))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut component = None;
+                while let Some(key) = map.next_key::<Key>()? {
+                    if component.is_some() {
+                        return Err(de::Error::duplicate_field("d"));
+                    }
+                    component = Some(match (key, map.next_value::<Deleted>()?) {
+                        (Key::D, Deleted::Len(n)) => Component::Delete(n),
+                        (Key::D, Deleted::Text(s)) => Component::DeleteText(s),
+                    });
+                }
+                component.ok_or_else(|| de::Error::missing_field("d"))
+            }
+        }
+
+        deserializer.deserialize_any(ComponentVisitor)
+    }
+}
+
+// The only key of a delete object.
+enum Key {
+    D,
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> Result<Key, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("`d`")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "d" => Ok(Key::D),
+                    _ => Err(E::unknown_field(value, &["d"])),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
+
+// The value of a delete object.
+enum Deleted {
+    Len(u64),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for Deleted {
+    fn deserialize<D>(deserializer: D) -> Result<Deleted, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DeletedVisitor;
+
+        impl<'de> Visitor<'de> for DeletedVisitor {
+            type Value = Deleted;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a non-negative integer or a string")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Deleted::Len(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Deleted::Text(value.to_owned()))
+            }
+        }
+
+        deserializer.deserialize_any(DeletedVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{selection::Bias, utilities::Rng, OperationSeq, Utf16OperationSeq};
+
+    // Operations as ShareDB sends them with the document before and after.
+    // Operations which are normalized like the ones of this crate encode to
+    // the same JSON again.
+    const TEXT_FIXTURES: &[(&str, &str, &str, bool)] = &[
+        ("[]", "abc", "abc", true),
+        (r#"["hello"]"#, "", "hello", true),
+        (r#"[5," world"]"#, "hello", "hello world", true),
+        (r#"[{"d":6}]"#, "hello world", "world", true),
+        (r#"[2,"xy",{"d":1},1,"z"]"#, "abcde", "abxydze", true),
+        (r#"[1,"🎉",{"d":2}]"#, "a😀b", "a🎉b", true),
+        (r#"[1,{"d":2},"🎉",0]"#, "a😀b", "a🎉b", false),
+    ];
+
+    const TEXT_UNICODE_FIXTURES: &[(&str, &str, &str, bool)] = &[
+        (r#"[1,"🎉",{"d":1}]"#, "a😀b", "a🎉b", true),
+        (r#"[{"d":"ab"},"x"]"#, "abc", "xc", false),
+        (r#"["😀😀",2,{"d":"😀"}]"#, "ab😀c", "😀😀abc", false),
+    ];
+
+    fn check_fixtures<S: Sequence<Slice = str, Owned = String>>(
+        fixtures: &[(&str, &str, &str, bool)],
+    ) {
+        for (json, before, after, normalized) in fixtures {
+            let components: Vec<Component> = serde_json::from_str(json).unwrap();
+            let o = GenericOperationSeq::<S>::from_sharedb(&components, S::len(before)).unwrap();
+            assert_eq!(o.apply(before).unwrap(), *after);
+            if *normalized {
+                assert_eq!(serde_json::to_string(&o.to_sharedb()).unwrap(), *json);
+            }
+        }
+    }
+
+    #[test]
+    fn fixtures() {
+        check_fixtures::<crate::Utf16>(TEXT_FIXTURES);
+        check_fixtures::<crate::Chars>(TEXT_UNICODE_FIXTURES);
+
+        let components: Vec<Component> = serde_json::from_str(r#"[{"d":"ab"},"x"]"#).unwrap();
+        assert_eq!(
+            components,
+            vec![
+                Component::DeleteText("ab".to_owned()),
+                Component::Insert("x".to_owned())
+            ]
+        );
+        for json in &[
+            "[-1]",
+            "[1.5]",
+            r#"[{"x":1}]"#,
+            r#"[{"d":1,"d":1}]"#,
+            "[{}]",
+        ] {
+            assert!(serde_json::from_str::<Vec<Component>>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::default();
+        for _ in 0..1000 {
+            let s = rng.gen_string(20);
+            let o = rng.gen_operation_seq(&s);
+            let doc_len = s.chars().count();
+            assert_eq!(
+                OperationSeq::from_sharedb(&o.to_sharedb(), doc_len).unwrap(),
+                o
+            );
+            let o: Utf16OperationSeq = o.convert(&s).unwrap();
+            let doc_len = s.encode_utf16().count();
+            assert_eq!(
+                Utf16OperationSeq::from_sharedb(&o.to_sharedb(), doc_len).unwrap(),
+                o
+            );
+        }
+
+        assert_eq!(
+            OperationSeq::from_sharedb(&[Component::Skip(2), Component::Delete(2)], 3),
+            Err(OTError::BaseLengthMismatch {
+                expected: 3,
+                actual: 4
+            })
+        );
+        assert_eq!(
+            OperationSeq::from_sharedb(&[Component::Skip(2), Component::Delete(u64::MAX)], 3),
+            Err(OTError::CountOverflow { index: 1 })
+        );
+    }
+
+    #[test]
+    fn cursors() {
+        // ShareDB keeps cursors in front of text inserted right at them.
+        let components: Vec<Component> = serde_json::from_str(r#"[1,"😀",{"d":1}]"#).unwrap();
+        let o = OperationSeq::from_sharedb(&components, 4).unwrap();
+        let cursors: Vec<usize> = (0..=4).map(|i| o.transform_index(i, Bias::Left)).collect();
+        assert_eq!(cursors, vec![0, 1, 2, 3, 4]);
+        let o = Utf16OperationSeq::from_sharedb(&components, 4).unwrap();
+        let cursors: Vec<usize> = (0..=4).map(|i| o.transform_index(i, Bias::Left)).collect();
+        assert_eq!(cursors, vec![0, 1, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,306 @@
+//! Undo and redo of local operations in a collaborative setting.
+//!
+//! The undo manager stores the inverse of every local operation. Operations
+//! of other users do not end up on the stacks, instead the stacks are
+//! transformed against them so that undoing only reverts the own changes.
+//!
+//! ```rust
+//! use operational_transform::{undo::UndoManager, OperationSeq};
+//! use std::time::Duration;
+//!
+//! let mut undo_manager = UndoManager::new(50, Duration::from_secs(0));
+//!
+//! let mut local = OperationSeq::default();
+//! local.insert("abc");
+//! undo_manager.add(local.invert("").unwrap()).unwrap();
+//! let doc = local.apply("").unwrap();
+//!
+//! let mut remote = OperationSeq::default();
+//! remote.insert("def");
+//! remote.retain(3);
+//! undo_manager.transform(&remote).unwrap();
+//! let doc = remote.apply(&doc).unwrap();
+//! assert_eq!(doc, "defabc");
+//!
+//! let undo = undo_manager.undo(&doc).unwrap().unwrap();
+//! let doc = undo.apply(&doc).unwrap();
+//! assert_eq!(doc, "def");
+//!
+//! let redo = undo_manager.redo(&doc).unwrap().unwrap();
+//! assert_eq!(redo.apply(&doc).unwrap(), "defabc");
+//! ```
+
+use crate::{OTError, OperationSeq};
+use bytecount::num_chars;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Keeps the undo and redo stack of a client.
+#[derive(Clone, Debug)]
+pub struct UndoManager {
+    // The maximum number of items kept on the undo stack.
+    max_items: usize,
+    // Operations added within this interval of the previous one are grouped
+    // into a single undo step.
+    group_interval: Duration,
+    // The time of the last operation that was added, if it can still be
+    // grouped with the next one.
+    last_add: Option<Instant>,
+    // Inverses of local operations, the most recent one last. The oldest one
+    // is dropped from the front once there are more than `max_items`.
+    undo_stack: VecDeque<OperationSeq>,
+    // Inverses of undone operations, the most recent one last.
+    redo_stack: VecDeque<OperationSeq>,
+}
+
+impl Default for UndoManager {
+    fn default() -> Self {
+        Self::new(50, Duration::from_secs(1))
+    }
+}
+
+impl UndoManager {
+    /// Creates an undo manager which keeps at most `max_items` undo steps and
+    /// groups operations that are added within `group_interval` of each other
+    /// into one undo step.
+    pub fn new(max_items: usize, group_interval: Duration) -> Self {
+        Self {
+            max_items,
+            group_interval,
+            last_add: None,
+            undo_stack: VecDeque::new(),
+            redo_stack: VecDeque::new(),
+        }
+    }
+
+    /// Adds the inverse of a local operation to the undo stack. The redo stack
+    /// is cleared.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operation cannot be grouped with the
+    /// previous undo step due to length conflicts.
+    pub fn add(&mut self, inverse: OperationSeq) -> Result<(), OTError> {
+        let now = Instant::now();
+        let group = match self.last_add {
+            Some(last_add) => now.duration_since(last_add) < self.group_interval,
+            None => false,
+        };
+        match self.undo_stack.back_mut() {
+            Some(last) if group => *last = inverse.compose(last)?,
+            _ => {
+                self.undo_stack.push_back(inverse);
+                if self.undo_stack.len() > self.max_items {
+                    self.undo_stack.pop_front();
+                }
+            }
+        }
+        self.redo_stack.clear();
+        self.last_add = Some(now);
+        Ok(())
+    }
+
+    /// Transforms both stacks against an operation of another user that has
+    /// just been applied to the document.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the operation does not fit the stacks due to
+    /// length conflicts. The stacks are left unchanged in that case.
+    pub fn transform(&mut self, operation: &OperationSeq) -> Result<(), OTError> {
+        let undo_stack = transform_stack(&self.undo_stack, operation)?;
+        let redo_stack = transform_stack(&self.redo_stack, operation)?;
+        self.undo_stack = undo_stack;
+        self.redo_stack = redo_stack;
+        Ok(())
+    }
+
+    /// Takes the latest undo step which has to be applied to the current
+    /// document `s`. Its inverse is pushed onto the redo stack.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the undo step cannot be applied to `s`.
+    pub fn undo(&mut self, s: &str) -> Result<Option<OperationSeq>, OTError> {
+        let operation = swap_top(&mut self.undo_stack, &mut self.redo_stack, s)?;
+        self.last_add = None;
+        Ok(operation)
+    }
+
+    /// Takes the latest redo step which has to be applied to the current
+    /// document `s`. Its inverse is pushed onto the undo stack.
+    ///
+    /// # Error
+    ///
+    /// Returns an `OTError` if the redo step cannot be applied to `s`.
+    pub fn redo(&mut self, s: &str) -> Result<Option<OperationSeq>, OTError> {
+        let operation = swap_top(&mut self.redo_stack, &mut self.undo_stack, s)?;
+        self.last_add = None;
+        Ok(operation)
+    }
+
+    /// Checks if there is an operation that can be undone.
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Checks if there is an operation that can be redone.
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+// Transforms every operation of the stack against `operation`, starting with
+// the most recent one which is based on the same document as `operation`.
+fn transform_stack(
+    stack: &VecDeque<OperationSeq>,
+    operation: &OperationSeq,
+) -> Result<VecDeque<OperationSeq>, OTError> {
+    let mut operation = operation.clone();
+    let mut new_stack = VecDeque::with_capacity(stack.len());
+    for o in stack.iter().rev() {
+        let (o_prime, operation_prime) = o.transform(&operation)?;
+        new_stack.push_front(o_prime);
+        operation = operation_prime;
+    }
+    Ok(new_stack)
+}
+
+// Pops the latest operation of `from` and pushes its inverse onto `to`.
+fn swap_top(
+    from: &mut VecDeque<OperationSeq>,
+    to: &mut VecDeque<OperationSeq>,
+    s: &str,
+) -> Result<Option<OperationSeq>, OTError> {
+    let len = num_chars(s.as_bytes());
+    match from.back() {
+        Some(operation) if operation.base_len() != len => Err(OTError::BaseLengthMismatch {
+            expected: operation.base_len(),
+            actual: len,
+        }),
+        Some(operation) => {
+            to.push_back(operation.invert(s)?);
+            Ok(from.pop_back())
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::Rng;
+
+    #[test]
+    fn undo_redo() {
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let mut undo_manager = UndoManager::new(50, Duration::from_secs(0));
+            let mut docs = vec![rng.gen_string(20)];
+            for _ in 0..5 {
+                let doc = docs.last().unwrap();
+                let o = rng.gen_operation_seq(doc);
+                undo_manager.add(o.invert(doc).unwrap()).unwrap();
+                docs.push(o.apply(doc).unwrap());
+            }
+            let mut doc = docs.pop().unwrap();
+            let last = doc.clone();
+            while let Some(o) = undo_manager.undo(&doc).unwrap() {
+                doc = o.apply(&doc).unwrap();
+                assert_eq!(doc, docs.pop().unwrap());
+            }
+            assert!(docs.is_empty());
+            while let Some(o) = undo_manager.redo(&doc).unwrap() {
+                doc = o.apply(&doc).unwrap();
+            }
+            assert_eq!(doc, last);
+        }
+    }
+
+    #[test]
+    fn group() {
+        let mut undo_manager = UndoManager::new(50, Duration::from_secs(3600));
+        let mut doc = String::new();
+        for s in &["a", "b", "c"] {
+            let mut o = OperationSeq::default();
+            o.retain(doc.len() as u64);
+            o.insert(s);
+            undo_manager.add(o.invert(&doc).unwrap()).unwrap();
+            doc = o.apply(&doc).unwrap();
+        }
+        assert_eq!(doc, "abc");
+        let o = undo_manager.undo(&doc).unwrap().unwrap();
+        assert_eq!(o.apply(&doc).unwrap(), "");
+        assert!(!undo_manager.can_undo());
+        assert!(undo_manager.can_redo());
+    }
+
+    #[test]
+    fn max_items() {
+        let mut undo_manager = UndoManager::new(2, Duration::from_secs(0));
+        let mut doc = String::new();
+        for s in &["a", "b", "c"] {
+            let mut o = OperationSeq::default();
+            o.retain(doc.len() as u64);
+            o.insert(s);
+            undo_manager.add(o.invert(&doc).unwrap()).unwrap();
+            doc = o.apply(&doc).unwrap();
+        }
+        for expected in &["ab", "a"] {
+            let o = undo_manager.undo(&doc).unwrap().unwrap();
+            doc = o.apply(&doc).unwrap();
+            assert_eq!(&doc, expected);
+        }
+        assert_eq!(undo_manager.undo(&doc).unwrap(), None);
+    }
+
+    #[test]
+    fn transform() {
+        let mut undo_manager = UndoManager::new(50, Duration::from_secs(0));
+        let mut local = OperationSeq::default();
+        local.retain(3);
+        local.insert("abc");
+        undo_manager.add(local.invert("xyz").unwrap()).unwrap();
+        let mut remote = OperationSeq::default();
+        remote.delete(1);
+        remote.retain(5);
+        undo_manager.transform(&remote).unwrap();
+        let o = undo_manager.undo("yzabc").unwrap().unwrap();
+        assert_eq!(o.apply("yzabc").unwrap(), "yz");
+        let o = undo_manager.redo("yz").unwrap().unwrap();
+        assert_eq!(o.apply("yz").unwrap(), "yzabc");
+    }
+
+    #[test]
+    fn transform_random() {
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let mut undo_manager = UndoManager::new(50, Duration::from_secs(0));
+            let s = rng.gen_string(20);
+            let local = rng.gen_operation_seq(&s);
+            let remote = rng.gen_operation_seq(&s);
+            let inverse = local.invert(&s).unwrap();
+            undo_manager.add(inverse.clone()).unwrap();
+            let (_, remote_prime) = local.transform(&remote).unwrap();
+            undo_manager.transform(&remote_prime).unwrap();
+            let doc = local.compose(&remote_prime).unwrap().apply(&s).unwrap();
+
+            // Undoing keeps the remote operation as if the local one never
+            // happened.
+            let (_, remote_without_local) = inverse.transform(&remote_prime).unwrap();
+            let o = undo_manager.undo(&doc).unwrap().unwrap();
+            let undone = o.apply(&doc).unwrap();
+            assert_eq!(undone, remote_without_local.apply(&s).unwrap());
+            assert!(!undo_manager.can_undo());
+
+            // Redoing restores the document before the undo.
+            let o = undo_manager.redo(&undone).unwrap().unwrap();
+            assert_eq!(o.apply(&undone).unwrap(), doc);
+            assert!(!undo_manager.can_redo());
+        }
+    }
+}
@@ -0,0 +1,530 @@
+// The walks over operation sequences that compose and transform them. They
+// are shared by the plain operations of the crate root and the rich text
+// operations, whose retains and inserts additionally carry a format.
+
+use crate::{sequence::Sequence, GenericOperation, OTError, Side};
+use std::{cmp, mem};
+
+// The format of a retain or an insert, which is borrowed from an operation
+// while it is walked. Plain operations carry no format at all.
+pub(crate) trait Format: Copy {
+    // The format of an operation that is produced by a walk.
+    type Owned: Default;
+
+    // Returns the format of a retain or an insert with `self` that is
+    // followed by a retain with `other`. `retain` is set if the result is a
+    // retain, which has to keep the removal of a format.
+    fn compose(self, other: Self, retain: bool) -> Self::Owned;
+
+    // Returns the format of a retain with `self` that is transformed against
+    // a concurrent retain with `other`. If both set the same format, the one
+    // of `self` wins if `wins` is set.
+    fn transform(self, other: Self, wins: bool) -> Self::Owned;
+
+    // Returns the format for a produced operation.
+    fn into_owned(self) -> Self::Owned;
+}
+
+impl Format for () {
+    type Owned = ();
+
+    #[inline]
+    fn compose(self, _other: (), _retain: bool) {}
+
+    #[inline]
+    fn transform(self, _other: (), _wins: bool) {}
+
+    #[inline]
+    fn into_owned(self) {}
+}
+
+// An operation borrowed from an operation sequence, or the part of it that is
+// left while two sequences are walked in parallel.
+pub(crate) enum Piece<'a, S: Sequence, F> {
+    Delete(u64),
+    Retain(u64, F),
+    // The inserted sequence together with its length.
+    Insert(&'a S::Slice, usize, F),
+}
+
+impl<'a, S: Sequence, F: Copy> Clone for Piece<'a, S, F> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, S: Sequence, F: Copy> Copy for Piece<'a, S, F> {}
+
+impl<'a, S: Sequence> From<&'a GenericOperation<S>> for Piece<'a, S, ()> {
+    #[inline]
+    fn from(op: &'a GenericOperation<S>) -> Self {
+        match op {
+            GenericOperation::Delete(n) => Piece::Delete(*n),
+            GenericOperation::Retain(n) => Piece::Retain(*n, ()),
+            GenericOperation::Insert(s) => Piece::Insert(s.as_slice(), s.unit_len(), ()),
+        }
+    }
+}
+
+impl<'a, S: Sequence, F: Copy> Piece<'a, S, F> {
+    // Returns the number of items the piece covers.
+    #[inline]
+    fn len(&self) -> u64 {
+        match self {
+            Piece::Delete(n) | Piece::Retain(n, _) => *n,
+            Piece::Insert(_, len, _) => *len as u64,
+        }
+    }
+
+    // Splits off the first `n` items of the piece, which must not be more
+    // than it covers. The rest is `None` if nothing is left. Returns `None` if
+    // an insert would be split inside of a character.
+    #[inline]
+    fn split(self, n: u64) -> Option<(Self, Option<Self>)> {
+        if n == self.len() {
+            return Some((self, None));
+        }
+        Some(match self {
+            Piece::Delete(i) => (Piece::Delete(n), Some(Piece::Delete(i - n))),
+            Piece::Retain(i, f) => (Piece::Retain(n, f), Some(Piece::Retain(i - n, f))),
+            Piece::Insert(s, len, f) => {
+                let (head, tail) = S::split_at(s, n as usize)?;
+                (
+                    Piece::Insert(head, n as usize, f),
+                    Some(Piece::Insert(tail, len - n as usize, f)),
+                )
+            }
+        })
+    }
+}
+
+// The operations of a sequence a stream of operations is transformed
+// against, which are looked up by their index.
+pub(crate) trait Ops<'a, S: Sequence, F>: Copy {
+    // Returns the operation at `index`.
+    fn get(self, index: usize) -> Option<Piece<'a, S, F>>;
+}
+
+impl<'a, S: Sequence> Ops<'a, S, ()> for &'a [GenericOperation<S>] {
+    #[inline]
+    fn get(self, index: usize) -> Option<Piece<'a, S, ()>> {
+        <[GenericOperation<S>]>::get(self, index).map(Piece::from)
+    }
+}
+
+impl<'a, S: Sequence, F: Copy> Ops<'a, S, F> for &[Piece<'a, S, F>] {
+    #[inline]
+    fn get(self, index: usize) -> Option<Piece<'a, S, F>> {
+        <[Piece<'a, S, F>]>::get(self, index).copied()
+    }
+}
+
+// Receives the operations produced by a walk.
+pub(crate) trait Sink<'a, S: Sequence, F> {
+    // Deletes `n` items.
+    fn delete(&mut self, n: u64);
+
+    // Retains `n` items and applies `format` to them.
+    fn retain(&mut self, n: u64, format: F);
+
+    // Inserts `s` of length `len` with `format`.
+    fn insert(&mut self, s: &'a S::Slice, len: usize, format: F);
+}
+
+impl<'a, S: Sequence, F> Piece<'a, S, F> {
+    // Pushes the piece onto `sink`.
+    #[inline]
+    pub(crate) fn push_onto<K: Sink<'a, S, F>>(self, sink: &mut K) {
+        match self {
+            Piece::Delete(n) => sink.delete(n),
+            Piece::Retain(n, f) => sink.retain(n, f),
+            Piece::Insert(s, len, f) => sink.insert(s, len, f),
+        }
+    }
+}
+
+// Collects pieces like an operation sequence does, i.e. consecutive retains
+// and deletes are merged and inserts are put in front of deletes at the same
+// position.
+impl<'a, S: Sequence> Sink<'a, S, ()> for Vec<Piece<'a, S, ()>> {
+    fn delete(&mut self, n: u64) {
+        match self.last_mut() {
+            Some(Piece::Delete(n_last)) => *n_last += n,
+            _ => self.push(Piece::Delete(n)),
+        }
+    }
+
+    fn retain(&mut self, n: u64, _format: ()) {
+        match self.last_mut() {
+            Some(Piece::Retain(n_last, _)) => *n_last += n,
+            _ => self.push(Piece::Retain(n, ())),
+        }
+    }
+
+    fn insert(&mut self, s: &'a S::Slice, len: usize, _format: ()) {
+        match self.last() {
+            Some(Piece::Delete(_)) => self.insert(self.len() - 1, Piece::Insert(s, len, ())),
+            _ => self.push(Piece::Insert(s, len, ())),
+        }
+    }
+}
+
+// Composes the consecutive operations `ops1` and `ops2` into `composed`.
+pub(crate) fn compose<'a, S, F, I1, I2, C>(
+    mut ops1: I1,
+    mut ops2: I2,
+    composed: &mut C,
+) -> Result<(), OTError>
+where
+    S: Sequence,
+    S::Slice: 'a,
+    F: Format,
+    I1: ExactSizeIterator<Item = Piece<'a, S, F>>,
+    I2: Iterator<Item = Piece<'a, S, F>>,
+    C: Sink<'a, S, F::Owned>,
+{
+    let len1 = ops1.len();
+    // The position in the sequence between both operations, which is only
+    // needed for error reporting.
+    let mut pos = 0;
+
+    let mut maybe_op1 = ops1.next();
+    let mut maybe_op2 = ops2.next();
+    loop {
+        match (maybe_op1, maybe_op2) {
+            (None, None) => return Ok(()),
+            (Some(Piece::Delete(i)), _) => {
+                composed.delete(i);
+                maybe_op1 = ops1.next();
+            }
+            (_, Some(Piece::Insert(s, len, f))) => {
+                composed.insert(s, len, f.into_owned());
+                maybe_op2 = ops2.next();
+            }
+            (None, _) => return Err(OTError::OperationMismatch { index: len1 }),
+            (_, None) => {
+                return Err(OTError::OperationMismatch {
+                    index: len1 - ops1.len() - 1,
+                })
+            }
+            (Some(op1), Some(op2)) => {
+                let n = cmp::min(op1.len(), op2.len());
+                pos += n as usize;
+                let (head1, tail1) = op1
+                    .split(n)
+                    .ok_or(OTError::NotACharBoundary { offset: pos })?;
+                match (head1, op2) {
+                    (Piece::Retain(_, f1), Piece::Retain(_, f2)) => {
+                        composed.retain(n, f1.compose(f2, true))
+                    }
+                    (Piece::Insert(s, _, f1), Piece::Retain(_, f2)) => {
+                        composed.insert(s, n as usize, f1.compose(f2, false))
+                    }
+                    (Piece::Retain(..), _) => composed.delete(n),
+                    // The insert is deleted again.
+                    _ => {}
+                }
+                maybe_op1 = match tail1 {
+                    Some(_) => tail1,
+                    None => ops1.next(),
+                };
+                maybe_op2 = match op2 {
+                    Piece::Retain(j, f2) if j > n => Some(Piece::Retain(j - n, f2)),
+                    Piece::Delete(j) if j > n => Some(Piece::Delete(j - n)),
+                    _ => ops2.next(),
+                };
+            }
+        }
+    }
+}
+
+// Transforms a stream of operations against the operations of a concurrent
+// sequence. The operations of the stream are pushed one after the other, so
+// that several transforms can be chained without collecting the operations
+// in between.
+pub(crate) struct Transform<'a, S: Sequence, F> {
+    // The index of the next concurrent operation.
+    index: usize,
+    // The part of the current concurrent operation that is left, or `None`
+    // if the next one has to be looked up.
+    op2: Option<Piece<'a, S, F>>,
+    // The side the inserts of the stream end up on.
+    side: Side,
+}
+
+impl<'a, S: Sequence, F: Format> Transform<'a, S, F> {
+    #[inline]
+    pub(crate) fn new(side: Side) -> Self {
+        Self {
+            index: 0,
+            op2: None,
+            side,
+        }
+    }
+
+    // Returns the current concurrent operation.
+    #[inline]
+    fn current<O: Ops<'a, S, F>>(&mut self, ops2: O) -> Option<Piece<'a, S, F>> {
+        if self.op2.is_none() {
+            self.op2 = ops2.get(self.index);
+            self.index += 1;
+        }
+        self.op2
+    }
+
+    // Transforms `op1` against `ops2`, which produces the operations of A'
+    // in `a_prime` and the ones of B' in `b_prime`. `index` is the index of
+    // `op1` for error reporting.
+    pub(crate) fn push<O, A, B>(
+        &mut self,
+        ops2: O,
+        mut op1: Piece<'a, S, F>,
+        index: usize,
+        a_prime: &mut A,
+        b_prime: &mut B,
+    ) -> Result<(), OTError>
+    where
+        O: Ops<'a, S, F>,
+        A: Sink<'a, S, F::Owned>,
+        B: Sink<'a, S, F::Owned>,
+    {
+        loop {
+            match (op1, self.current(ops2)) {
+                (Piece::Insert(s, len, f), op2)
+                    if self.side == Side::Left || !matches!(op2, Some(Piece::Insert(..))) =>
+                {
+                    a_prime.insert(s, len, f.into_owned());
+                    b_prime.retain(len as u64, Default::default());
+                    return Ok(());
+                }
+                (_, Some(Piece::Insert(s, len, f))) => {
+                    a_prime.retain(len as u64, Default::default());
+                    b_prime.insert(s, len, f.into_owned());
+                    self.op2 = None;
+                }
+                (_, None) => return Err(OTError::OperationMismatch { index }),
+                (_, Some(op2)) => {
+                    // Only retains and deletes are left, which are always
+                    // split.
+                    let n = cmp::min(op1.len(), op2.len());
+                    let (head1, tail1) = op1.split(n).unwrap_or((op1, None));
+                    let (head2, tail2) = op2.split(n).unwrap_or((op2, None));
+                    match (head1, head2) {
+                        (Piece::Retain(_, f1), Piece::Retain(_, f2)) => {
+                            a_prime.retain(n, f1.transform(f2, self.side == Side::Left));
+                            b_prime.retain(n, f2.transform(f1, self.side == Side::Right));
+                        }
+                        (Piece::Delete(_), Piece::Retain(..)) => a_prime.delete(n),
+                        (Piece::Retain(..), Piece::Delete(_)) => b_prime.delete(n),
+                        // Both delete the same items.
+                        _ => {}
+                    }
+                    self.op2 = tail2;
+                    match tail1 {
+                        Some(tail1) => op1 = tail1,
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+
+    // Ends the stream, which consisted of `len1` operations, and transforms
+    // the concurrent inserts at its end.
+    pub(crate) fn finish<O, A, B>(
+        &mut self,
+        ops2: O,
+        len1: usize,
+        a_prime: &mut A,
+        b_prime: &mut B,
+    ) -> Result<(), OTError>
+    where
+        O: Ops<'a, S, F>,
+        A: Sink<'a, S, F::Owned>,
+        B: Sink<'a, S, F::Owned>,
+    {
+        loop {
+            match self.current(ops2) {
+                None => return Ok(()),
+                Some(Piece::Insert(s, len, f)) => {
+                    a_prime.retain(len as u64, Default::default());
+                    b_prime.insert(s, len, f.into_owned());
+                    self.op2 = None;
+                }
+                Some(_) => return Err(OTError::OperationMismatch { index: len1 }),
+            }
+        }
+    }
+}
+
+// Transforms the operations `ops1` against the concurrent operations `ops2`
+// and produces A' in `a_prime` and B' in `b_prime`.
+pub(crate) fn transform<'a, S, F, I1, O, A, B>(
+    ops1: I1,
+    ops2: O,
+    side: Side,
+    a_prime: &mut A,
+    b_prime: &mut B,
+) -> Result<(), OTError>
+where
+    S: Sequence,
+    S::Slice: 'a,
+    F: Format,
+    I1: ExactSizeIterator<Item = Piece<'a, S, F>>,
+    O: Ops<'a, S, F>,
+    A: Sink<'a, S, F::Owned>,
+    B: Sink<'a, S, F::Owned>,
+{
+    let len1 = ops1.len();
+    let mut transform = Transform::new(side);
+    for (index, op1) in ops1.enumerate() {
+        transform.push(ops2, op1, index, a_prime, b_prime)?;
+    }
+    transform.finish(ops2, len1, a_prime, b_prime)
+}
+
+// The operations a transform of a chain produces for the next one. Deletes
+// are held back until a retain follows, so that inserts at the same position
+// come first just like in an operation sequence.
+struct Link<'b, 'a, S: Sequence> {
+    pieces: &'b mut Vec<Piece<'a, S, ()>>,
+    // The number of items deleted in front of the pieces to come.
+    delete: &'b mut u64,
+}
+
+impl<'b, 'a, S: Sequence> Link<'b, 'a, S> {
+    // Releases the held back deletes.
+    #[inline]
+    fn flush(&mut self) {
+        if *self.delete > 0 {
+            self.pieces.push(Piece::Delete(mem::take(self.delete)));
+        }
+    }
+}
+
+impl<'b, 'a, S: Sequence> Sink<'a, S, ()> for Link<'b, 'a, S> {
+    #[inline]
+    fn delete(&mut self, n: u64) {
+        *self.delete += n;
+    }
+
+    #[inline]
+    fn retain(&mut self, n: u64, _format: ()) {
+        self.flush();
+        self.pieces.push(Piece::Retain(n, ()));
+    }
+
+    #[inline]
+    fn insert(&mut self, s: &'a S::Slice, len: usize, _format: ()) {
+        self.pieces.push(Piece::Insert(s, len, ()));
+    }
+}
+
+// A transform of a chain together with the deletes it holds back.
+type ChainLink<'a, S> = (Transform<'a, S, ()>, u64);
+
+// Transforms the operations `ops1` against a chain of consecutive concurrent
+// sequences, whose operations `history` returns by their index in the chain.
+// A' is produced in `a_prime` and the transformed concurrent sequences in
+// `history_prime`, which has an entry for every sequence of the chain. Every
+// operation is pushed through the whole chain before the next one, so that
+// the operation is never collected in between.
+pub(crate) fn transform_chain<'a, S, I1, O, H, A, B>(
+    ops1: I1,
+    history: H,
+    side: Side,
+    a_prime: &mut A,
+    history_prime: &mut [B],
+) -> Result<(), OTError>
+where
+    S: Sequence,
+    S::Slice: 'a,
+    I1: ExactSizeIterator<Item = Piece<'a, S, ()>>,
+    O: Ops<'a, S, ()>,
+    H: Fn(usize) -> O,
+    A: Sink<'a, S, ()>,
+    B: Sink<'a, S, ()>,
+{
+    let len1 = ops1.len();
+    let mut chain: Vec<ChainLink<'a, S>> = history_prime
+        .iter()
+        .map(|_| (Transform::new(side), 0))
+        .collect();
+    // The pieces that are passed on from one transform to the next.
+    let mut pieces = Vec::new();
+    let mut next_pieces = Vec::new();
+
+    for (index, op1) in ops1.enumerate() {
+        pieces.push(op1);
+        pass(
+            &mut chain,
+            0,
+            &history,
+            index,
+            &mut pieces,
+            &mut next_pieces,
+            history_prime,
+        )?;
+        for piece in pieces.drain(..) {
+            piece.push_onto(a_prime);
+        }
+    }
+    // The concurrent inserts at the end of a sequence of the chain are passed
+    // on to the rest of the chain, together with the deletes held back.
+    for j in 0..chain.len() {
+        let (transform, delete) = &mut chain[j];
+        let mut link = Link {
+            pieces: &mut pieces,
+            delete,
+        };
+        transform.finish(history(j), len1, &mut link, &mut history_prime[j])?;
+        link.flush();
+        pass(
+            &mut chain,
+            j + 1,
+            &history,
+            len1,
+            &mut pieces,
+            &mut next_pieces,
+            history_prime,
+        )?;
+        for piece in pieces.drain(..) {
+            piece.push_onto(a_prime);
+        }
+    }
+    Ok(())
+}
+
+// Pushes `pieces` through the transforms of `chain` from `start` on, using
+// `next_pieces` as scratch space. The pieces that leave the chain end up in
+// `pieces` again. `index` is the index of the operation the pieces stem from for error
+// reporting.
+fn pass<'a, S, O, H, B>(
+    chain: &mut [ChainLink<'a, S>],
+    start: usize,
+    history: &H,
+    index: usize,
+    pieces: &mut Vec<Piece<'a, S, ()>>,
+    next_pieces: &mut Vec<Piece<'a, S, ()>>,
+    history_prime: &mut [B],
+) -> Result<(), OTError>
+where
+    S: Sequence,
+    S::Slice: 'a,
+    O: Ops<'a, S, ()>,
+    H: Fn(usize) -> O,
+    B: Sink<'a, S, ()>,
+{
+    for (j, (transform, delete)) in chain.iter_mut().enumerate().skip(start) {
+        let mut link = Link {
+            pieces: &mut *next_pieces,
+            delete,
+        };
+        for op1 in pieces.drain(..) {
+            transform.push(history(j), op1, index, &mut link, &mut history_prime[j])?;
+        }
+        mem::swap(pieces, next_pieces);
+    }
+    Ok(())
+}